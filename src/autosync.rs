@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use gyroflow_core::StabilizationManager;
+use half::f16;
+use gyroflow_core::synchronization::{ AutosyncProcess, SyncParams };
+use itertools::Either;
+use ofx::{ BitDepth, ClipInstance };
+use parking_lot::Mutex;
+
+// Sync points evenly spread over the clip, as fractions of its duration
+const SYNC_POINTS: [f64; 5] = [0.1, 0.3, 0.5, 0.7, 0.9];
+const TIME_PER_SYNC_POINT_MS: f64 = 1000.0;
+const SEARCH_SIZE_MS: f64 = 5000.0;
+// Optical flow doesn't need the full resolution, and the frames of all sync points are kept in memory
+const MAX_HEIGHT: usize = 720;
+
+// Grayscale frame for the optical flow
+pub struct LumaFrame {
+    pub timestamp_us: i64,
+    pub frame_no: usize,
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+pub fn new_process(stab: &StabilizationManager) -> Result<AutosyncProcess, String> {
+    let sync_params = SyncParams {
+        search_size:        SEARCH_SIZE_MS,
+        max_sync_points:    SYNC_POINTS.len(),
+        every_nth_frame:    1,
+        time_per_syncpoint: TIME_PER_SYNC_POINT_MS,
+        of_method:          2,
+        offset_method:      2,
+        ..Default::default()
+    };
+    AutosyncProcess::from_manager(stab, &SYNC_POINTS, sync_params, "synchronize".into(), Arc::new(AtomicBool::new(false)))
+        .map_err(|_| "Failed to initialize autosync".to_owned())
+}
+
+// Converts the RGBA, RGB or Alpha image from the host, the rows are bottom-up in OFX.
+// `stride` is the signed distance between the rows, starting at the bottom one
+pub unsafe fn luma_frame(data: *const u8, depth: BitDepth, channels: usize, size: (usize, usize, isize), frame_no: usize, fps: f64) -> LumaFrame {
+    let (width, height, stride) = size;
+    let step = height.div_ceil(MAX_HEIGHT).max(1);
+    let (out_width, out_height) = (width / step, height / step);
+    let mut pixels = Vec::with_capacity(out_width * out_height);
+    for y in 0..out_height {
+        let row = data.offset((height - 1 - y * step) as isize * stride);
+        for x in 0..out_width {
+            let px = x * step * channels;
+            // Single channel images are used as they are
+            let (g, b) = if channels == 1 { (0, 0) } else { (1, 2) };
+            let rgb = match depth {
+                BitDepth::Byte  => { let p = row.add(px); [*p as f32 / 255.0, *p.add(g) as f32 / 255.0, *p.add(b) as f32 / 255.0] },
+                BitDepth::Short => { let p = (row as *const u16).add(px); [*p as f32 / 65535.0, *p.add(g) as f32 / 65535.0, *p.add(b) as f32 / 65535.0] },
+                BitDepth::Half  => { let p = (row as *const f16).add(px); [(*p).to_f32(), (*p.add(g)).to_f32(), (*p.add(b)).to_f32()] },
+                BitDepth::Float => { let p = (row as *const f32).add(px); [*p, *p.add(g), *p.add(b)] },
+                BitDepth::None  => [0.0; 3],
+            };
+            let luma = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            pixels.push((luma.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+    LumaFrame {
+        timestamp_us: (frame_no as f64 / fps * 1_000_000.0).round() as i64,
+        frame_no,
+        width: out_width,
+        height: out_height,
+        pixels,
+    }
+}
+
+// Reads the frames around the sync points. Clip images can only be fetched inside an action, so it's done before the job is started
+pub fn read_frames(clip: &ClipInstance, frame_numbers: &[usize], fps: f64) -> Result<Vec<LumaFrame>, String> {
+    frame_numbers.iter().map(|&frame_no| {
+        let error = |_| format!("Failed to read frame {frame_no} of the clip");
+        let image = clip.get_image(frame_no as f64).map_err(error)?;
+        let bounds = image.get_bounds().map_err(error)?;
+        let (width, height) = ((bounds.x2 - bounds.x1) as usize, (bounds.y2 - bounds.y1) as usize);
+        let stride = image.get_row_bytes().map_err(error)? as isize;
+        let channels = super::gyroflow::num_channels(image.get_components().map_err(error)?);
+        let depth = image.get_pixel_depth().map_err(error)?;
+        let data = image.get_data().map_err(error)? as *const u8;
+        Ok(unsafe { luma_frame(data, depth, channels, (width, height, stride), frame_no, fps) })
+    }).collect()
+}
+
+// Runs the optical flow and the sync, returns the offsets as (timestamp_us, offset_ms)
+pub fn run(mut sync: AutosyncProcess, frames: Vec<LumaFrame>) -> Result<Vec<(i64, f64)>, String> {
+    let result = Arc::new(Mutex::new(None));
+    {
+        let result = result.clone();
+        sync.on_finished(move |arg| {
+            if let Either::Left(offsets) = arg {
+                *result.lock() = Some(offsets);
+            }
+        });
+    }
+    for frame in &frames {
+        sync.feed_frame(frame.timestamp_us, frame.frame_no, frame.width as u32, frame.height as u32, frame.width, &frame.pixels);
+    }
+    sync.finished_feeding_frames();
+
+    let offsets = result.lock().take().unwrap_or_default();
+    if offsets.is_empty() {
+        return Err("No sync points were found, make sure there's enough motion in the clip".to_owned());
+    }
+    Ok(offsets.into_iter().map(|(timestamp_ms, offset_ms, _cost)| ((timestamp_ms * 1000.0).round() as i64, offset_ms)).collect())
+}
+
+// Frames around the sync points, which have to be read from the clip
+pub fn frames_to_read(sync: &AutosyncProcess, fps: f64, num_frames: usize) -> Vec<usize> {
+    let mut frames = Vec::new();
+    for (start_ms, end_ms) in sync.get_ranges() {
+        let first = (start_ms * fps / 1000.0).floor().max(0.0) as usize;
+        let last = ((end_ms * fps / 1000.0).ceil() as usize).min(num_frames.saturating_sub(1));
+        frames.extend(first..=last);
+    }
+    frames.dedup();
+    frames
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering::SeqCst };
+use std::thread::JoinHandle;
+
+use ofx::*;
+use super::fuscript::*;
+
+// Asks the host to render the instance again, when something changed outside of the actions.
+// Other threads only set a flag, the hidden `RenderRevision` param is changed by the next action on the host's thread (see `apply`),
+// which invalidates the cached frames in every host. Resolve is also asked directly through fuscript, which doesn't use the instance
+#[derive(Clone)]
+pub struct RenderTrigger {
+    revision: ParamHandle<Double>,
+    pending: Arc<AtomicBool>,
+    // Cleared when the instance is destroyed, so jobs finishing after it don't ask for anything
+    alive: Arc<AtomicBool>,
+}
+unsafe impl Send for RenderTrigger { }
+unsafe impl Sync for RenderTrigger { }
+
+impl RenderTrigger {
+    pub fn new(revision: ParamHandle<Double>) -> Self {
+        Self { revision, pending: Arc::new(AtomicBool::new(false)), alive: Arc::new(AtomicBool::new(true)) }
+    }
+
+    // Safe to call from any thread
+    pub fn trigger(&self) {
+        if !self.alive.load(SeqCst) { return; }
+        self.pending.store(true, SeqCst);
+        if CurrentFileInfo::is_available() {
+            CurrentFileInfo::trigger_render();
+        }
+    }
+
+    // Changes `RenderRevision` if a render was requested, only from an action which can change params
+    pub fn apply(&self) -> Result<()> {
+        if self.alive.load(SeqCst) && self.pending.swap(false, SeqCst) {
+            self.revision.set_value(self.revision.get_value()? + 1.0)?;
+        }
+        Ok(())
+    }
+
+    pub fn cancel(&self) {
+        self.alive.store(false, SeqCst);
+    }
+}
+
+// Work done outside of the render action, identified by the cache key it's computing.
+// When it's done, the host is asked to render again and the result is picked up by that render
+pub struct BackgroundJob<T> {
+    pub key: String,
+    handle: JoinHandle<T>,
+}
+
+impl<T: Send + 'static> BackgroundJob<T> {
+    pub fn spawn<F: FnOnce() -> T + Send + 'static>(key: &str, trigger: &RenderTrigger, f: F) -> Self {
+        let trigger = trigger.clone();
+        let handle = std::thread::spawn(move || {
+            let result = f();
+            trigger.trigger();
+            result
+        });
+        Self { key: key.to_owned(), handle }
+    }
+
+    pub fn is_running(slot: &Option<Self>, key: &str) -> bool {
+        slot.as_ref().map(|x| x.key == key).unwrap_or_default()
+    }
+
+    // Takes the result of the job computing `key`, or returns None if it's still running. With `wait`, blocks until it's done.
+    // A job computing anything else is outdated, so it's detached and its result is dropped
+    pub fn take_result(slot: &mut Option<Self>, key: &str, wait: bool) -> Option<T> {
+        match slot.take() {
+            Some(job) if job.key == key => {
+                if !wait && !job.handle.is_finished() {
+                    *slot = Some(job);
+                    return None;
+                }
+                match job.handle.join() {
+                    Ok(result) => Some(result),
+                    Err(_) => {
+                        log::error!("Background job {key} panicked");
+                        None
+                    }
+                }
+            },
+            _ => None
+        }
+    }
+}
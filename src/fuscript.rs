@@ -87,10 +87,7 @@ impl CurrentFileInfo {
                         *current_file_info.lock() = Some(info);
                         current_file_info_pending.store(true, SeqCst);
 
-                        // Trigger render
-                        let script = "c = Resolve():GetProjectManager():GetCurrentProject():GetCurrentTimeline():GetCurrentVideoItem();
-                                          c:SetProperty('FlipX', c:GetProperty('FlipX'))";
-                        let _ = cmd.args(["-x", &script]).spawn();
+                        Self::trigger_render();
                     }
                 } else {
                     log::debug!("fuscript stdout: {stdout}");
@@ -105,6 +102,18 @@ impl CurrentFileInfo {
         });
     }
 
+    // Re-setting any property of the current clip makes Resolve render it again
+    pub fn trigger_render() {
+        let Some(fuscript) = Self::get_fuscript() else { return; };
+        let mut cmd = std::process::Command::new(fuscript);
+        #[cfg(target_os = "windows")]
+        { use std::os::windows::process::CommandExt; cmd.creation_flags(0x08000000); } // CREATE_NO_WINDOW
+
+        let script = "c = Resolve():GetProjectManager():GetCurrentProject():GetCurrentTimeline():GetCurrentVideoItem();
+                          c:SetProperty('FlipX', c:GetProperty('FlipX'))";
+        let _ = cmd.args(["-x", &script]).spawn();
+    }
+
     fn parse_duration(v: &str, fps: f64) -> f64 {
         let parts = v.replace(";", ":").split(':').filter_map(|x| x.parse::<f64>().ok()).collect::<Vec<_>>();
         if parts.len() == 4 {
@@ -1,13 +1,20 @@
-use std::sync::Arc;
+use std::sync::{ Arc, Weak };
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::SeqCst;
 
-use gyroflow_core::{ StabilizationManager, stabilization::{ RGBA8, RGBA16, RGBAf, RGBAf16 }, keyframes::{ KeyframeType, KeyframeManager }, filesystem };
+use gyroflow_core::{ StabilizationManager, stabilization::{ RGBA8, RGBA16, RGBAf, RGBAf16, RGB8, RGB16, RGBf, RGBf16, Luma8, Luma16, Lumaf, Lumaf16, Interpolation, ComputeParams, undistort_points_with_rolling_shutter }, keyframes::{ KeyframeType, KeyframeManager }, filesystem };
 use gyroflow_core::gpu::{ BufferDescription, Buffers, BufferSource };
 use lru::LruCache;
 use ofx::*;
 use parking_lot::{ Mutex, RwLock };
 use super::fuscript::*;
+use super::manager_cache::{ ManagerCache, derive_manager };
+use super::project_watcher::ProjectWatcher;
+use super::background_job::{ BackgroundJob, RenderTrigger };
+use super::smoothing_params::{ self, SmoothingParam, SmoothingParamHandle };
+use super::autosync;
+use super::lens_profiles;
+use super::tiles::{ self, CpuImage, TiledRender };
 
 plugin_module!(
     "nl.smslv.gyroflowofx.fisheyestab_v1",
@@ -18,11 +25,48 @@ plugin_module!(
 
 // We should cache managers globally because it's common to have the effect applied to the same clip and cut the clip into multiple pieces
 // We don't want to create a new manager for each piece of the same clip
-// Cache key is specific enough
+// There are two layers: the loaded project with gyro data, keyed by the project file and embedded data (`project:` keys),
+// and the managers set up for rendering, keyed by output format and a hash of the instance parameters.
+// Pieces with the same parameters share the rendering manager, the others share at least the loaded gyro data.
+// The global cache owns the managers and is bounded by their estimated memory, instances only keep weak references
 lazy_static::lazy_static! {
-    static ref MANAGER_CACHE: Mutex<LruCache<String, Arc<StabilizationManager>>> = Mutex::new(LruCache::new(std::num::NonZeroUsize::new(8).unwrap()));
+    static ref MANAGER_CACHE: Mutex<ManagerCache> = Mutex::new(ManagerCache::new());
 }
 
+// Cells of the grid mapped through the stabilization to find the region of interest of the source, per axis
+const ROI_GRID: usize = 16;
+// Pixels around the region of interest needed by the interpolation
+const ROI_MARGIN: f64 = 4.0;
+
+// Animation curves are sampled until the linear interpolation is within this fraction of the values (at least of 1.0),
+// or up to this many halvings between two keys
+const CURVE_TOLERANCE: f64 = 0.002;
+const MAX_CURVE_DEPTH: u32 = 8;
+
+// Values of the `ZoomMode` choice
+const ZOOM_DISABLED: Int = 0;
+const ZOOM_DYNAMIC:  Int = 1;
+const ZOOM_STATIC:   Int = 2;
+// Zooming of the loaded project, so projects saved before the param existed render the same
+const ZOOM_PROJECT:  Int = 3;
+
+// Relative difference from the `MaxZoom` limit at which a frame is counted as clamped
+const ZOOM_LIMIT_TOLERANCE: f64 = 1e-6;
+
+// Values of the `AnalyzeCropMode` choice
+const CROP_WHOLE_CLIP:  Int = 0;
+const CROP_PER_SEGMENT: Int = 1;
+
+// Values of the `Interpolation` choice
+const INTERPOLATION_AUTO:     Int = 0;
+const INTERPOLATION_BILINEAR: Int = 1;
+const INTERPOLATION_BICUBIC:  Int = 2;
+const INTERPOLATION_LANCZOS4: Int = 3;
+
+// Values of the `HorizonLockMode` choice
+const HORIZON_LOCK_ROLL:       Int = 0;
+const HORIZON_LOCK_ROLL_PITCH: Int = 1;
+
 #[derive(Default)]
 struct GyroflowPlugin {
 	host_supports_multiple_clip_depths: Bool,
@@ -32,53 +76,315 @@ struct GyroflowPlugin {
 
 struct KeyframableParams {
     fov: ParamHandle<Double>,
+    zoom_window: ParamHandle<Double>,
+    max_zoom: ParamHandle<Double>,
+    stabilization_amount: ParamHandle<Double>,
+    sync_offset: ParamHandle<Double>,
     smoothness: ParamHandle<Double>,
+    smoothness_pitch: ParamHandle<Double>,
+    smoothness_yaw: ParamHandle<Double>,
+    smoothness_roll: ParamHandle<Double>,
     lens_correction_strength: ParamHandle<Double>,
     horizon_lock_amount: ParamHandle<Double>,
     horizon_lock_roll: ParamHandle<Double>,
+    horizon_lock_pitch: ParamHandle<Double>,
     positionx: ParamHandle<Double>,
     positiony: ParamHandle<Double>,
     rotation: ParamHandle<Double>,
     video_speed: ParamHandle<Double>,
     use_gyroflows_keyframes: ParamHandle<Bool>,
     use_gyroflows_cached: bool,
-
-    cached_keyframes: KeyframeManager
+    smoothing_algorithm: ParamHandle<Int>,
+    // Keyframable parameters of the smoothing algorithms: (algorithm, type, param)
+    smoothing: Vec<(usize, KeyframeType, ParamHandle<Double>)>,
+
+    cached_keyframes: KeyframeManager,
+    // There are no keyframe types for these, they're applied by the plugin
+    cached_max_zoom: Vec<(i64, f64)>,
+    cached_stab_amount: Vec<(i64, f64)>,
+    cached_sync_offset: Vec<(i64, f64)>,
+    cached_hash: u64
 }
 unsafe impl Send for KeyframableParams { }
 unsafe impl Sync for KeyframableParams { }
 
 impl KeyframableParams {
-    pub fn cache_keyframes(&mut self, num_frames: usize, fps: f64) {
+    pub fn cache_keyframes(&mut self, fps: f64) {
+        use std::hash::{ Hash, Hasher };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
         self.cached_keyframes.clear();
         self.use_gyroflows_cached = self.use_gyroflows_keyframes.get_value().unwrap_or_default();
+        self.use_gyroflows_cached.hash(&mut hasher);
         macro_rules! cache_key {
             ($typ:expr, $param:expr, $scale:expr) => {
+                format!("{:?}", $typ).hash(&mut hasher);
                 if $param.get_num_keys().unwrap_or_default() > 0 {
-                    for t in 0..num_frames {
-                        let time = t as f64;
+                    for (time, v) in Self::sample_curve(&$param) {
                         let timestamp_us = ((time / fps * 1_000_000.0)).round() as i64;
 
-                        if let Ok(v) = $param.get_value_at_time(time) {
-                            self.cached_keyframes.set(&$typ, timestamp_us, v / $scale);
-                        }
+                        self.cached_keyframes.set(&$typ, timestamp_us, v / $scale);
+                        (timestamp_us, (v / $scale).to_bits()).hash(&mut hasher);
                     }
                 } else {
                     if let Ok(v) = $param.get_value() {
                         self.cached_keyframes.set(&$typ, 0, v / $scale);
+                        (0i64, (v / $scale).to_bits()).hash(&mut hasher);
                     }
                 }
             };
         }
         cache_key!(KeyframeType::Fov,                       self.fov,                      1.0);
+        cache_key!(KeyframeType::ZoomingSpeed,              self.zoom_window,              1.0);
         cache_key!(KeyframeType::SmoothingParamSmoothness,  self.smoothness,               1.0);
+        cache_key!(KeyframeType::SmoothingParamPitch,       self.smoothness_pitch,         1.0);
+        cache_key!(KeyframeType::SmoothingParamYaw,         self.smoothness_yaw,           1.0);
+        cache_key!(KeyframeType::SmoothingParamRoll,        self.smoothness_roll,          1.0);
         cache_key!(KeyframeType::LensCorrectionStrength,    self.lens_correction_strength, 100.0);
         cache_key!(KeyframeType::LockHorizonAmount,         self.horizon_lock_amount,      1.0);
         cache_key!(KeyframeType::LockHorizonRoll,           self.horizon_lock_roll,        1.0);
+        cache_key!(KeyframeType::LockHorizonPitch,          self.horizon_lock_pitch,       1.0);
         cache_key!(KeyframeType::VideoSpeed,                self.video_speed,              100.0);
         cache_key!(KeyframeType::VideoRotation,             self.rotation,                 1.0);
         cache_key!(KeyframeType::ZoomingCenterX,            self.positionx,                100.0);
         cache_key!(KeyframeType::ZoomingCenterY,            self.positiony,                100.0);
+
+        self.cached_max_zoom = Self::cache_hashed_curve(&self.max_zoom, "MaxZoom", fps, &mut hasher);
+        // Not affected by `UseGyroflowsKeyframes`, because Gyroflow doesn't have it
+        self.cached_stab_amount = Self::cache_hashed_curve(&self.stabilization_amount, "StabilizationAmount", fps, &mut hasher);
+        self.cached_sync_offset = Self::cache_hashed_curve(&self.sync_offset, "SyncOffset", fps, &mut hasher);
+
+        // Only the selected algorithm, others may use the same keyframe types
+        let algorithm = self.smoothing_algorithm.get_value().unwrap_or_default() as usize;
+        algorithm.hash(&mut hasher);
+        let smoothing = std::mem::take(&mut self.smoothing);
+        for (_, typ, param) in smoothing.iter().filter(|x| x.0 == algorithm) {
+            cache_key!(typ, param, 1.0);
+        }
+        self.smoothing = smoothing;
+
+        self.cached_hash = hasher.finish();
+    }
+
+    // (timestamp_us, value) pairs of a param which is applied by the plugin, a single pair if it's not animated
+    fn cache_curve(param: &ParamHandle<Double>, fps: f64) -> Vec<(i64, f64)> {
+        if param.get_num_keys().unwrap_or_default() > 0 {
+            Self::sample_curve(param).into_iter().map(|(time, v)| ((time / fps * 1_000_000.0).round() as i64, v)).collect()
+        } else {
+            param.get_value().map(|v| vec![(0, v)]).unwrap_or_default()
+        }
+    }
+
+    // `cache_curve`, with the name and the values added to the hash
+    fn cache_hashed_curve(param: &ParamHandle<Double>, name: &str, fps: f64, hasher: &mut impl std::hash::Hasher) -> Vec<(i64, f64)> {
+        use std::hash::Hash;
+        let curve = Self::cache_curve(param, fps);
+        name.hash(hasher);
+        for (timestamp_us, v) in &curve {
+            (timestamp_us, v.to_bits()).hash(hasher);
+        }
+        curve
+    }
+
+    pub fn curve_value_at(curve: &[(i64, f64)], timestamp_us: i64) -> f64 {
+        match curve.iter().position(|x| x.0 >= timestamp_us) {
+            None    => curve.last().map(|x| x.1).unwrap_or_default(),
+            Some(0) => curve[0].1,
+            Some(i) => {
+                let (a, b) = (curve[i - 1], curve[i]);
+                a.1 + (b.1 - a.1) * (timestamp_us - a.0) as f64 / (b.0 - a.0) as f64
+            }
+        }
+    }
+
+    // Samples the host's animation curve at the key times, and in between them only where it's not linear.
+    // Values between the samples are interpolated linearly by the KeyframeManager
+    fn sample_curve(param: &ParamHandle<Double>) -> Vec<(f64, f64)> {
+        let mut keys = Vec::new();
+        for i in 0..param.get_num_keys().unwrap_or_default() {
+            if let Ok(time) = param.get_key_time(i) {
+                if let Ok(v) = param.get_value_at_time(time) {
+                    keys.push((time, v));
+                }
+            }
+        }
+        let mut samples = Vec::with_capacity(keys.len());
+        for w in keys.windows(2) {
+            samples.push(w[0]);
+            Self::subdivide_curve(param, w[0], w[1], MAX_CURVE_DEPTH, &mut samples);
+        }
+        samples.extend(keys.last());
+        samples
+    }
+    fn subdivide_curve(param: &ParamHandle<Double>, a: (f64, f64), b: (f64, f64), depth: u32, samples: &mut Vec<(f64, f64)>) {
+        // Whole frames only, that's the finest resolution used by the renders
+        let at = |f: f64| (a.0 + (b.0 - a.0) * f).round();
+        let (t1, t2, t3) = (at(0.25), at(0.5), at(0.75));
+        if depth == 0 || t2 <= a.0 || t2 >= b.0 { return; }
+
+        let lerp = |t: f64| a.1 + (b.1 - a.1) * (t - a.0) / (b.0 - a.0);
+        let tolerance = CURVE_TOLERANCE * a.1.abs().max(b.1.abs()).max(1.0);
+        let is_linear = |t: f64, v: f64| (v - lerp(t)).abs() <= tolerance;
+        let Ok(v2) = param.get_value_at_time(t2) else { return; };
+        // The midpoint alone can't tell a symmetric ease in/out curve from a line
+        let linear = is_linear(t2, v2) && [t1, t3].iter().all(|t| param.get_value_at_time(*t).map(|v| is_linear(*t, v)).unwrap_or(true));
+        if linear { return; }
+
+        Self::subdivide_curve(param, a, (t2, v2), depth - 1, samples);
+        samples.push((t2, v2));
+        Self::subdivide_curve(param, (t2, v2), b, depth - 1, samples);
+    }
+}
+
+fn has_lens_profile(stab: &StabilizationManager) -> bool {
+    !stab.lens.read().fisheye_params.camera_matrix.is_empty()
+}
+
+pub fn num_channels(components: ImageComponent) -> usize {
+    match components {
+        ImageComponent::Alpha => 1,
+        ImageComponent::RGB   => 3,
+        _ => 4
+    }
+}
+fn bytes_per_pixel(depth: BitDepth, components: ImageComponent) -> usize {
+    num_channels(components) * match depth { BitDepth::None => 0, BitDepth::Byte => 1, BitDepth::Short => 2, BitDepth::Half => 2, BitDepth::Float => 4 }
+}
+
+enum LoadError {
+    Project(String),
+    FileInfo(String),
+}
+struct LoadedProject {
+    stab: std::result::Result<StabilizationManager, LoadError>,
+    // Problems which didn't stop the loading, they're shown in the status
+    warnings: Vec<String>,
+    pending_video_file: bool,
+}
+
+struct ProjectSource {
+    stab: StabilizationManager,
+    path: String,
+    embedded_lens: String,
+    embedded_preset: String,
+    motion_data_file: String,
+    project_data: String,
+    autosync_offsets: String,
+    pending_video_file: bool,
+}
+impl ProjectSource {
+    fn load(mut self) -> LoadedProject {
+        let pending_video_file = self.pending_video_file;
+        let autosync_offsets = std::mem::take(&mut self.autosync_offsets);
+        let path = self.path.clone();
+        let mut warnings = Vec::new();
+        let stab = self.load_stab(&mut warnings);
+        if let Ok(stab) = &stab {
+            Self::load_offsets(stab, &autosync_offsets, &path);
+        }
+        LoadedProject { stab, warnings, pending_video_file }
+    }
+
+    // Runs in the background, so it can't show any dialogs
+    fn load_stab(self, warnings: &mut Vec<String>) -> std::result::Result<StabilizationManager, LoadError> {
+        let stab = self.stab;
+        let path = &self.path;
+        if !path.ends_with(".gyroflow") {
+            // Try to load from video file
+            // let mut metadata = None;
+            // if path.to_ascii_lowercase().ends_with(".mxf") || path.to_ascii_lowercase().ends_with(".braw") {
+            //     let lock = self.current_file_info.lock();
+            //     if let Some(ref current_file) = *lock {
+            //         metadata = Some(VideoMetadata {
+            //             duration_s: current_file.duration_s,
+            //             fps: current_file.fps,
+            //             width: current_file.width,
+            //             height: current_file.height,
+            //             rotation: 0
+            //         });
+            //     }
+            // }
+
+            match stab.load_video_file(&filesystem::path_to_url(path), None, true) {
+                Ok(md) => {
+                    if !self.motion_data_file.is_empty() {
+                        if let Err(e) = stab.load_gyro_data(&filesystem::path_to_url(&self.motion_data_file), false, &Default::default(), |_|(), Arc::new(AtomicBool::new(false))) {
+                            // The embedded project has the motion data when the file isn't available, eg. on another computer
+                            if self.project_data.is_empty() {
+                                return Err(LoadError::FileInfo(format!("Failed to load motion data {}: {e:?}", self.motion_data_file)));
+                            }
+                            log::warn!("Failed to load motion data {}: {e:?}, using the embedded project", self.motion_data_file);
+                            let mut is_preset = false;
+                            stab.import_gyroflow_data(self.project_data.as_bytes(), true, None, |_|(), Arc::new(AtomicBool::new(false)), &mut is_preset, true)
+                                .map_err(|e| LoadError::Project(e.to_string()))?;
+                            return Ok(stab);
+                        }
+                    }
+                    if !self.embedded_lens.is_empty() {
+                        if let Err(e) = stab.load_lens_profile(&self.embedded_lens) {
+                            warnings.push(format!("Failed to load lens profile: {e:?}"));
+                        }
+                    } else if !has_lens_profile(&stab) {
+                        if let Some(camera) = md.camera_identifier.as_ref() {
+                            let size = stab.params.read().size;
+                            if let Some((name, id)) = lens_profiles::best_match(&camera.brand, &camera.model, &camera.lens_model, size) {
+                                log::info!("Lens profile for {} {} {}: {name}", camera.brand, camera.model, camera.lens_model);
+                                if let Some(Err(e)) = lens_profiles::profile_json(&id).map(|json| stab.load_lens_profile(&json)) {
+                                    log::error!("Failed to load lens profile {name}: {e:?}");
+                                }
+                            }
+                        }
+                    }
+                    if !self.embedded_preset.is_empty() {
+                        let mut is_preset = false;
+                        if let Err(e) = stab.import_gyroflow_data(self.embedded_preset.as_bytes(), true, None, |_|(), Arc::new(AtomicBool::new(false)), &mut is_preset, true) {
+                            warnings.push(format!("Failed to load preset: {e:?}"));
+                        }
+                    }
+                    if md.rotation != 0 {
+                        let r = ((360 - md.rotation) % 360) as f64;
+                        stab.params.write().video_rotation = r;
+                    }
+                },
+                Err(e) => {
+                    if !self.project_data.is_empty() {
+                        let mut is_preset = false;
+                        stab.import_gyroflow_data(self.project_data.as_bytes(), true, None, |_|(), Arc::new(AtomicBool::new(false)), &mut is_preset, true)
+                            .map_err(|e| LoadError::Project(e.to_string()))?;
+                    } else {
+                        return Err(LoadError::FileInfo(format!("{e:?}")));
+                    }
+                }
+            }
+        } else {
+            let project_data = {
+                if !self.project_data.is_empty() {
+                    self.project_data
+                } else if let Ok(data) = std::fs::read_to_string(path) {
+                    data
+                } else {
+                    "".to_string()
+                }
+            };
+            let mut is_preset = false;
+            stab.import_gyroflow_data(project_data.as_bytes(), true, Some(&filesystem::path_to_url(path)), |_|(), Arc::new(AtomicBool::new(false)), &mut is_preset, true)
+                .map_err(|e| LoadError::Project(e.to_string()))?;
+        }
+        Ok(stab)
+    }
+
+    // Offsets found by autosync are kept in the `AutosyncOffsets` param, so they're used regardless of the embedded project data.
+    // They replace the project's offsets, but only for the file they were found for
+    fn load_offsets(stab: &StabilizationManager, autosync_offsets: &str, path: &str) {
+        let Ok(synced) = serde_json::from_str::<serde_json::Value>(autosync_offsets) else { return; };
+        if synced["path"].as_str() != Some(path) { return; }
+        let Some(offsets) = synced["offsets"].as_object() else { return; };
+        let mut gyro = stab.gyro.write();
+        gyro.clear_offsets();
+        for (timestamp_us, offset) in offsets {
+            if let (Ok(timestamp_us), Some(offset)) = (timestamp_us.parse::<i64>(), offset.as_f64()) {
+                gyro.set_offset(timestamp_us, offset);
+            }
+        }
     }
 }
 
@@ -93,24 +399,60 @@ struct InstanceData {
     param_project_data: ParamHandle<String>,
     param_embedded_lens: ParamHandle<String>,
     param_embedded_preset: ParamHandle<String>,
+    param_smoothing_algorithm_name: ParamHandle<String>,
+    param_autosync_offsets: ParamHandle<String>,
+    param_motion_data_file: ParamHandle<String>,
+    param_lens_search: ParamHandle<String>,
+    param_lens_profile_name: ParamHandle<String>,
     param_project_path: ParamHandle<String>,
     param_disable_stretch: ParamHandle<Bool>,
     param_status: ParamHandle<Bool>,
     param_open_in_gyroflow: ParamHandle<Bool>,
     param_toggle_overview: ParamHandle<Bool>,
     param_reload_project: ParamHandle<Bool>,
+    param_save_project: ParamHandle<Bool>,
+    param_auto_reload: ParamHandle<Bool>,
+    param_auto_reload_values: ParamHandle<Bool>,
     param_dont_draw_outside: ParamHandle<Bool>,
     param_include_project_data: ParamHandle<Bool>,
     param_input_rotation: ParamHandle<Double>,
-    gyrodata: LruCache<String, Arc<StabilizationManager>>,
+    param_per_axis: ParamHandle<Bool>,
+    param_zoom_mode: ParamHandle<Int>,
+    param_analyze_crop_mode: ParamHandle<Int>,
+    param_horizon_lock_mode: ParamHandle<Int>,
+    param_interpolation: ParamHandle<Int>,
+    param_analyze_segment: ParamHandle<Double>,
+    smoothing_params: Vec<(SmoothingParam, SmoothingParamHandle)>,
+    gyrodata: LruCache<String, Weak<StabilizationManager>>,
+    project_job: Option<BackgroundJob<LoadedProject>>,
+    setup_job: Option<BackgroundJob<StabilizationManager>>,
+    // Key of the project which failed to load, it's passed through until the project or its settings change
+    failed_project_key: Option<String>,
 
     reload_values_from_project: bool,
+    // Whether the reload resets `SyncOffset`, its keyframes are kept unless the user confirmed it
+    reset_sync_offset: bool,
+    project_watcher: Option<ProjectWatcher>,
+    zoom_limited: Option<((usize, u64), usize)>,
+    // Sync offsets of the loaded project, without the `SyncOffset` param
+    project_offsets: Vec<(i64, f64)>,
+    // `adaptive_zoom_window` of the loaded project, used by `ZOOM_PROJECT`
+    project_zoom_window: f64,
+    autosync_job: Option<BackgroundJob<std::result::Result<Vec<(i64, f64)>, String>>>,
+    autosync_error: Option<String>,
+    load_warnings: Vec<String>,
+    render_trigger: RenderTrigger,
+    tiled_render: Option<TiledRender>,
+    // (name, id) of the profiles matching `LensSearch`, and the selected one. None until one is picked with Previous/Next
+    lens_results: Vec<(String, String)>,
+    lens_result_index: Option<usize>,
+    // Description of the lens profile picked from the video metadata, when no profile is embedded. Empty if nothing matched
+    auto_lens: Option<String>,
 
     original_video_size: (usize, usize),
     original_output_size: (usize, usize),
     num_frames: usize,
     fps: f64,
-    ever_changed: bool,
 
     current_file_info_pending: Arc<AtomicBool>,
     current_file_info: Arc<Mutex<Option<CurrentFileInfo>>>,
@@ -119,7 +461,9 @@ struct InstanceData {
 }
 impl Drop for InstanceData {
     fn drop(&mut self) {
-        self.clear_stab();
+        // The param handles are gone with the instance, running jobs and the project watcher must not use them
+        self.render_trigger.cancel();
+        self.release_stab();
     }
 }
 
@@ -127,22 +471,57 @@ impl InstanceData {
     fn update_loaded_state(&mut self, loaded: bool) {
         let mut kparams = self.keyframable_params.write();
         let _ = kparams.fov.set_enabled(loaded);
+        let _ = self.param_zoom_mode.set_enabled(loaded);
+        let _ = kparams.max_zoom.set_enabled(loaded);
+        let _ = kparams.zoom_window.set_enabled(loaded && self.param_zoom_mode.get_value().unwrap_or_default() == ZOOM_DYNAMIC);
         let _ = kparams.smoothness.set_enabled(loaded);
+        let _ = kparams.stabilization_amount.set_enabled(loaded);
+        let _ = kparams.sync_offset.set_enabled(loaded);
+        let _ = self.param_analyze_segment.set_enabled(loaded && self.param_analyze_crop_mode.get_value().unwrap_or_default() == CROP_PER_SEGMENT);
+        let per_axis = self.param_per_axis.get_value().unwrap_or_default();
+        let _ = self.param_per_axis.set_enabled(loaded);
+        let _ = kparams.smoothness_pitch.set_enabled(loaded && per_axis);
+        let _ = kparams.smoothness_yaw.set_enabled(loaded && per_axis);
+        let _ = kparams.smoothness_roll.set_enabled(loaded && per_axis);
         let _ = kparams.lens_correction_strength.set_enabled(loaded);
         let _ = kparams.horizon_lock_amount.set_enabled(loaded);
         let _ = kparams.horizon_lock_roll.set_enabled(loaded);
+        let _ = self.param_horizon_lock_mode.set_enabled(loaded);
+        let _ = kparams.horizon_lock_pitch.set_enabled(loaded && self.param_horizon_lock_mode.get_value().unwrap_or_default() == HORIZON_LOCK_ROLL_PITCH);
         let _ = kparams.positionx.set_enabled(loaded);
         let _ = kparams.positiony.set_enabled(loaded);
         let _ = kparams.rotation.set_enabled(loaded);
         let _ = kparams.video_speed.set_enabled(loaded);
+        let _ = kparams.smoothing_algorithm.set_enabled(loaded);
+        let algorithm = kparams.smoothing_algorithm.get_value().unwrap_or_default() as usize;
+        for (p, handle) in &self.smoothing_params {
+            let _ = handle.set_enabled(loaded && p.algorithm == algorithm);
+        }
         let _ = self.param_disable_stretch.set_enabled(loaded);
         let _ = self.param_toggle_overview.set_enabled(loaded);
         let _ = self.param_reload_project.set_enabled(loaded);
+        let _ = self.param_save_project.set_enabled(loaded);
+        let _ = self.param_auto_reload_values.set_enabled(loaded && self.param_auto_reload.get_value().unwrap_or_default());
         let _ = self.param_status.set_label(if loaded { "OK" } else { "Project not loaded" });
         let _ = self.param_status.set_value(loaded);
         let _ = self.param_open_in_gyroflow.set_label(if loaded { "Open in Gyroflow" } else { "Open Gyroflow" });
     }
 
+    fn set_no_project_status(&mut self) -> Result<()> {
+        self.update_loaded_state(false);
+        self.param_status.set_hint("Select a Gyroflow project or a video file. The clip is passed through until then")?;
+        Ok(())
+    }
+
+    fn set_loading_status(&self) -> Result<()> {
+        self.param_status.set_label("Loading…")?;
+        self.param_status.set_hint("Loading the project and computing the stabilization in the background")?;
+        if self.param_status.get_value()? {
+            self.param_status.set_value(false)?;
+        }
+        Ok(())
+    }
+
     fn set_keyframe_provider(&self, stab: &StabilizationManager) {
         let kparams = self.keyframable_params.clone();
         stab.keyframes.write().set_custom_provider(move |kf, typ, timestamp_ms| -> Option<f64> {
@@ -152,7 +531,73 @@ impl InstanceData {
         });
     }
 
-    fn gyrodata(&mut self, bit_depth: BitDepth, output_rect: RectI, loading_pending_video_file: bool) -> Result<Arc<StabilizationManager>> {
+    // Keyframed values are provided by the keyframe provider, the rest is set directly on the algorithm.
+    // Only the `edited` param is set, or without it the ones changed from their defaults, so the values of the project are kept otherwise
+    fn apply_smoothing(&self, stab: &StabilizationManager, edited: Option<&str>) -> Result<()> {
+        let algorithm = self.keyframable_params.read().smoothing_algorithm.get_value()? as usize;
+        let mut smoothing = stab.smoothing.write();
+        if smoothing_params::algorithm_index(&smoothing) != algorithm {
+            smoothing.set_current(algorithm);
+        }
+        for (p, handle) in self.smoothing_params.iter().filter(|(p, _)| p.algorithm == algorithm) {
+            let value = handle.get_value()?;
+            if edited.map(|x| x == p.ofx_name).unwrap_or(value != p.default) {
+                smoothing.current_mut().set_parameter(&p.name, value);
+            }
+        }
+        let per_axis = self.param_per_axis.get_value()?;
+        if smoothing_params::has_parameter(&smoothing, "per_axis") && edited.map(|x| x == "PerAxisSmoothness").unwrap_or(per_axis) {
+            smoothing.current_mut().set_parameter("per_axis", if per_axis { 1.0 } else { 0.0 });
+        }
+        Ok(())
+    }
+
+    // The choice is stored by index, the name is stored as well so the same algorithm is selected when gyroflow-core adds new ones
+    fn restore_smoothing_algorithm(&self) -> Result<()> {
+        let kparams = self.keyframable_params.read();
+        match smoothing_params::algorithm_by_name(&self.param_smoothing_algorithm_name.get_value()?) {
+            Some(algorithm) if algorithm as i32 != kparams.smoothing_algorithm.get_value()? => kparams.smoothing_algorithm.set_value(algorithm as i32)?,
+            Some(_) => { },
+            None => self.store_smoothing_algorithm_name(kparams.smoothing_algorithm.get_value()? as usize)?,
+        }
+        Ok(())
+    }
+    fn store_smoothing_algorithm_name(&self, algorithm: usize) -> Result<()> {
+        self.param_smoothing_algorithm_name.set_value(smoothing_params::algorithm_names().get(algorithm).cloned().unwrap_or_default())
+    }
+
+    // The amount, roll and pitch angles are provided as keyframes, only locking the pitch is set directly
+    fn apply_horizon_lock(&self, stab: &StabilizationManager) -> Result<()> {
+        stab.smoothing.write().horizon_lock.lock_pitch = self.param_horizon_lock_mode.get_value()? == HORIZON_LOCK_ROLL_PITCH;
+        Ok(())
+    }
+
+    // `adaptive_zoom_window` is 0 for no zooming, negative for static zoom and the window length in seconds for dynamic zoom
+    fn zoom_window(&self) -> Result<f64> {
+        Ok(match self.param_zoom_mode.get_value()? {
+            ZOOM_DYNAMIC => self.keyframable_params.read().zoom_window.get_value()?.max(0.01),
+            ZOOM_STATIC  => -1.0,
+            ZOOM_PROJECT => self.project_zoom_window,
+            _            => 0.0
+        })
+    }
+    fn apply_zoom(&self, stab: &StabilizationManager) -> Result<()> {
+        stab.params.write().adaptive_zoom_window = self.zoom_window()?;
+        Ok(())
+    }
+
+    // Automatic interpolation uses a cheap kernel while scrubbing or for draft renders, and the best one for the final render
+    fn interpolation(&self, draft: bool) -> Result<Interpolation> {
+        Ok(match self.param_interpolation.get_value()? {
+            INTERPOLATION_BILINEAR => Interpolation::Bilinear,
+            INTERPOLATION_BICUBIC  => Interpolation::Bicubic,
+            INTERPOLATION_LANCZOS4 => Interpolation::Lanczos4,
+            _ if draft             => Interpolation::Bilinear,
+            _                      => Interpolation::Lanczos4,
+        })
+    }
+
+    fn gyrodata(&mut self, bit_depth: BitDepth, output_rect: RectI, interpolation: Interpolation, loading_pending_video_file: bool, background: bool) -> Result<Option<Arc<StabilizationManager>>> {
         let disable_stretch = self.param_disable_stretch.get_value()?;
 
         let source_rect = self.source_clip.get_region_of_definition(0.0)?;
@@ -163,234 +608,826 @@ impl InstanceData {
             y2: source_rect.y2 as i32
         };
         if source_rect.x1 != output_rect.x1 || source_rect.x2 != output_rect.x2 || source_rect.y1 != output_rect.y1 || source_rect.y2 != output_rect.y2 {
-            source_rect = self.source_clip.get_image(0.0)?.get_bounds()?;
+            source_rect = self.source_clip.get_image(0.0)?.get_region_of_definition()?;
         }
         let in_size = ((source_rect.x2 - source_rect.x1) as usize, (source_rect.y2 - source_rect.y1) as usize);
         let out_size = ((output_rect.x2 - output_rect.x1) as usize, (output_rect.y2 - output_rect.y1) as usize);
 
-        let instance_id = self.param_instance_id.get_value()?;
         let path = self.param_project_path.get_value()?;
         if path.is_empty() {
             self.update_loaded_state(false);
             return Err(Error::UnknownError);
         }
-        let key = format!("{path}{bit_depth:?}{in_size:?}{out_size:?}{disable_stretch}{instance_id}");
-        let cloned = MANAGER_CACHE.lock().get(&key).map(Arc::clone);
-        let stab = if let Some(stab) = cloned {
+        // The loaded project is a part of the key, so the managers set up from an older version of the file are never used.
+        // The managers are shared between instances, so the interpolation is a part of it too instead of being changed per render
+        let project_key = self.project_key(&path)?;
+        let key_prefix = format!("{path}|{project_key}|{bit_depth:?}{in_size:?}{out_size:?}{disable_stretch}|{}", interpolation as i32);
+        let key = format!("{key_prefix}|{:016x}", self.params_hash());
+        let cloned = MANAGER_CACHE.lock().get(&key);
+        if let Some(stab) = cloned {
             // Cache it in this instance as well
             if !self.gyrodata.contains(&key) {
-                self.gyrodata.put(key.to_owned(), stab.clone());
+                self.cache_locally(&key, &stab);
             }
             self.set_keyframe_provider(&stab);
-            stab
+            return Ok(Some(stab));
+        }
+        // It may be already computed in the background
+        if let Some(stab) = BackgroundJob::take_result(&mut self.setup_job, &key, !background) {
+            return Ok(Some(self.insert_manager(&key, stab)));
+        }
+        if BackgroundJob::is_running(&self.setup_job, &key) {
+            return Ok(None);
+        }
+
+        // Gyro data is loaded and parsed once per project, every instance works on its own copy of it
+        let Some(base) = self.loaded_project(&path, loading_pending_video_file, background)? else {
+            return Ok(None);
+        };
+        let stab = derive_manager(&base);
+
+        self.embed_project_data(&path, &stab)?;
+        if !path.ends_with(".gyroflow") && self.reload_values_from_project {
+            let rotation = stab.params.read().video_rotation;
+            if rotation != 0.0 {
+                self.param_input_rotation.set_value(rotation)?;
+            }
+        }
+
+        self.auto_lens = if !path.ends_with(".gyroflow") && self.param_embedded_lens.get_value()?.is_empty() {
+            let lens = stab.lens.read();
+            Some(if has_lens_profile(&stab) { format!("{} {} {}", lens.camera_brand, lens.camera_model, lens.lens_model).trim().to_owned() } else { String::new() })
         } else {
-            let mut stab = StabilizationManager::default();
-            {
-                // Find first lens profile database with loaded profiles
-                let lock = MANAGER_CACHE.lock();
-                for (_, v) in lock.iter() {
-                    if v.lens_profile_db.read().loaded {
-                        stab.lens_profile_db = v.lens_profile_db.clone();
-                        break;
-                    }
-                }
+            None
+        };
+        if let Some(lens) = self.auto_lens.as_ref().filter(|x| !x.is_empty()) {
+            let name = format!("{lens} (auto)");
+            if self.param_lens_profile_name.get_value()? != name {
+                self.param_lens_profile_name.set_value(name)?;
             }
+        }
 
-            if !path.ends_with(".gyroflow") {
-                // Try to load from video file
-                // let mut metadata = None;
-                // if path.to_ascii_lowercase().ends_with(".mxf") || path.to_ascii_lowercase().ends_with(".braw") {
-                //     let lock = self.current_file_info.lock();
-                //     if let Some(ref current_file) = *lock {
-                //         metadata = Some(VideoMetadata {
-                //             duration_s: current_file.duration_s,
-                //             fps: current_file.fps,
-                //             width: current_file.width,
-                //             height: current_file.height,
-                //             rotation: 0
-                //         });
-                //     }
-                // }
-
-                match stab.load_video_file(&filesystem::path_to_url(&path), None, true) {
-                    Ok(md) => {
-                        if let Ok(d) = self.param_embedded_lens.get_value() {
-                            if !d.is_empty() {
-                                if let Err(e) = stab.load_lens_profile(&d) {
-                                    rfd::MessageDialog::new()
-                                        .set_description(&format!("Failed to load lens profile: {e:?}"))
-                                        .show();
-                                }
+        let loaded = {
+            stab.params.write().calculate_ramped_timestamps(&stab.keyframes.read(), false, true);
+            let params = stab.params.read();
+            self.original_video_size = params.size;
+            self.original_output_size = params.output_size;
+            self.num_frames = params.frame_count;
+            self.fps = params.fps;
+            let loaded = params.duration_ms > 0.0;
+            if loaded && self.reload_values_from_project {
+                self.reload_values_from_project = false;
+                let smooth = stab.smoothing.read();
+                let smoothness = smooth.current().get_parameter("smoothness");
+
+                let kparams = self.keyframable_params.read();
+                kparams.fov.set_value(params.fov)?;
+                if params.adaptive_zoom_window > 0.0001 {
+                    self.param_zoom_mode.set_value(ZOOM_DYNAMIC)?;
+                    kparams.zoom_window.set_value(params.adaptive_zoom_window)?;
+                } else {
+                    self.param_zoom_mode.set_value(if params.adaptive_zoom_window < -0.9 { ZOOM_STATIC } else { ZOOM_DISABLED })?;
+                }
+                kparams.smoothness.set_value(smoothness)?;
+                if smoothing_params::has_parameter(&smooth, "per_axis") {
+                    self.param_per_axis.set_value(smooth.current().get_parameter("per_axis") > 0.5)?;
+                    kparams.smoothness_pitch.set_value(smooth.current().get_parameter("smoothness_pitch"))?;
+                    kparams.smoothness_yaw.set_value(smooth.current().get_parameter("smoothness_yaw"))?;
+                    kparams.smoothness_roll.set_value(smooth.current().get_parameter("smoothness_roll"))?;
+                }
+                kparams.lens_correction_strength.set_value((params.lens_correction_amount * 100.0).min(100.0))?;
+                kparams.horizon_lock_amount.set_value(if smooth.horizon_lock.lock_enabled { smooth.horizon_lock.horizonlockpercent } else { 0.0 })?;
+                kparams.horizon_lock_roll.set_value(if smooth.horizon_lock.lock_enabled { smooth.horizon_lock.horizonroll } else { 0.0 })?;
+                self.param_horizon_lock_mode.set_value(if smooth.horizon_lock.lock_pitch { HORIZON_LOCK_ROLL_PITCH } else { HORIZON_LOCK_ROLL })?;
+                kparams.horizon_lock_pitch.set_value(if smooth.horizon_lock.lock_pitch { smooth.horizon_lock.horizonpitch } else { 0.0 })?;
+                kparams.video_speed.set_value(params.video_speed * 100.0)?;
+                kparams.positionx.set_value(params.adaptive_zoom_center_offset.0 * 100.0)?;
+                kparams.positiony.set_value(params.adaptive_zoom_center_offset.1 * 100.0)?;
+                kparams.rotation.set_value(params.video_rotation)?;
+                // The project's own offsets are always used, this is only the adjustment on top of them
+                if self.reset_sync_offset {
+                    kparams.sync_offset.delete_all_keys()?;
+                    kparams.sync_offset.set_value(0.0)?;
+                } else {
+                    log::warn!("Keeping the keyframes of SyncOffset after reloading the project");
+                }
+
+                let algorithm = smoothing_params::algorithm_index(&smooth);
+                kparams.smoothing_algorithm.set_value(algorithm as i32)?;
+                self.store_smoothing_algorithm_name(algorithm)?;
+                for (p, handle) in self.smoothing_params.iter().filter(|(p, _)| p.algorithm == algorithm) {
+                    handle.set_value(smooth.current().get_parameter(&p.name))?;
+                }
+
+                let keyframes = stab.keyframes.read();
+                let all_keys = keyframes.get_all_keys();
+                kparams.use_gyroflows_keyframes.set_value(!all_keys.is_empty())?;
+                for k in all_keys {
+                    if let Some(keys) = keyframes.get_keyframes(k) {
+                        if !keys.is_empty() {
+                            macro_rules! set_keys {
+                                ($name:expr, $scale:expr) => {
+                                    $name.delete_all_keys()?;
+                                    for (ts, v) in keys {
+                                        let ts = if k == &KeyframeType::VideoSpeed { params.get_source_timestamp_at_ramped_timestamp(*ts) } else { *ts };
+                                        let time = (((ts as f64 / 1000.0) * params.fps) / 1000.0).round();
+                                        $name.set_value_at_time(time, v.value * $scale)?;
+                                    }
+                                };
                             }
-                        }
-                        if let Ok(d) = self.param_embedded_preset.get_value() {
-                            if !d.is_empty() {
-                                let mut is_preset = false;
-                                if let Err(e) = stab.import_gyroflow_data(d.as_bytes(), true, None, |_|(), Arc::new(AtomicBool::new(false)), &mut is_preset, true) {
-                                    rfd::MessageDialog::new()
-                                        .set_description(&format!("Failed to load preset: {e:?}"))
-                                        .show();
+                            match k {
+                                KeyframeType::Fov                      => { set_keys!(kparams.fov,                      1.0); },
+                                KeyframeType::ZoomingSpeed             => { set_keys!(kparams.zoom_window,              1.0); },
+                                KeyframeType::SmoothingParamSmoothness => { set_keys!(kparams.smoothness,               1.0); },
+                                KeyframeType::SmoothingParamPitch      => { set_keys!(kparams.smoothness_pitch,         1.0); },
+                                KeyframeType::SmoothingParamYaw        => { set_keys!(kparams.smoothness_yaw,           1.0); },
+                                KeyframeType::SmoothingParamRoll       => { set_keys!(kparams.smoothness_roll,          1.0); },
+                                KeyframeType::LensCorrectionStrength   => { set_keys!(kparams.lens_correction_strength, 100.0); },
+                                KeyframeType::LockHorizonAmount        => { set_keys!(kparams.horizon_lock_amount,      1.0); },
+                                KeyframeType::LockHorizonRoll          => { set_keys!(kparams.horizon_lock_roll,        1.0); },
+                                KeyframeType::LockHorizonPitch         => { set_keys!(kparams.horizon_lock_pitch,       1.0); },
+                                KeyframeType::VideoSpeed               => { set_keys!(kparams.video_speed,              100.0); },
+                                KeyframeType::VideoRotation            => { set_keys!(kparams.rotation,                 1.0); },
+                                KeyframeType::ZoomingCenterX           => { set_keys!(kparams.positionx,                100.0); },
+                                KeyframeType::ZoomingCenterY           => { set_keys!(kparams.positiony,                100.0); },
+                                _ => {
+                                    if let Some((_, _, param)) = kparams.smoothing.iter().find(|(alg, typ, _)| *alg == algorithm && typ == k) {
+                                        set_keys!(param, 1.0);
+                                    }
                                 }
                             }
                         }
-                        if self.param_include_project_data.get_value()? {
-                            if let Ok(data) = stab.export_gyroflow_data(gyroflow_core::GyroflowProjectType::WithGyroData, "{}", None) {
-                                self.param_project_data.set_value(data)?;
-                            }
-                        }
-                        if md.rotation != 0 {
-                            let r = ((360 - md.rotation) % 360) as f64;
-                            self.param_input_rotation.set_value(r)?;
-                            stab.params.write().video_rotation = r;
-                        }
-                        if !stab.gyro.read().file_metadata.read().has_accurate_timestamps && loading_pending_video_file {
-                            self.open_gyroflow();
-                        }
-                    },
-                    Err(e) => {
-                        let embedded_data = self.param_project_data.get_value()?;
-                        if !embedded_data.is_empty() {
-                            let mut is_preset = false;
-                            stab.import_gyroflow_data(embedded_data.as_bytes(), true, None, |_|(), Arc::new(AtomicBool::new(false)), &mut is_preset, true).map_err(|e| {
-                                log::error!("load_gyro_data error: {}", &e);
-                                self.update_loaded_state(false);
-                                Error::UnknownError
-                            })?;
-                        } else {
-                            log::error!("An error occured: {e:?}");
-                            self.update_loaded_state(false);
-                            self.param_status.set_label("Failed to load file info!")?;
-                            self.param_status.set_hint(&format!("Error loading {path}: {e:?}."))?;
-                            if loading_pending_video_file {
-                                self.open_gyroflow();
-                            }
-                            return Err(Error::UnknownError);
-                        }
                     }
                 }
-            } else {
-                let project_data = {
-                    if self.param_include_project_data.get_value()? && !self.param_project_data.get_value()?.is_empty() {
-                        self.param_project_data.get_value()?
-                    } else if let Ok(data) = std::fs::read_to_string(&path) {
-                        if self.param_include_project_data.get_value()? {
-                            self.param_project_data.set_value(data.clone())?;
-                        } else {
-                            self.param_project_data.set_value("".to_string())?;
+            }
+            self.keyframable_params.write().cache_keyframes(self.fps.max(1.0));
+            loaded
+        };
+        self.project_zoom_window = base.params.read().adaptive_zoom_window;
+        self.apply_smoothing(&stab, None)?;
+        self.apply_horizon_lock(&stab)?;
+        self.apply_zoom(&stab)?;
+        // Applied after embedding, otherwise the embedded project would include it and it would be applied twice
+        self.project_offsets = base.gyro.read().get_offsets().iter().map(|(k, v)| (*k, *v)).collect();
+        Self::apply_sync_offset(&stab, &self.project_offsets, &self.keyframable_params.read().cached_sync_offset);
+
+        self.update_loaded_state(loaded);
+
+        // Parameters are known now, another instance may have already computed the same thing (eg. other cut pieces of the clip)
+        let key = format!("{key_prefix}|{:016x}", self.params_hash());
+        let cloned = MANAGER_CACHE.lock().get(&key);
+        if let Some(stab) = cloned {
+            if !self.gyrodata.contains(&key) {
+                self.cache_locally(&key, &stab);
+            }
+            self.set_keyframe_provider(&stab);
+            return Ok(Some(stab));
+        }
+
+        self.set_keyframe_provider(&stab);
+
+        let overview = self.param_toggle_overview.get_value()?;
+        let use_gyroflows_keyframes = self.keyframable_params.read().use_gyroflows_keyframes.get_value()?;
+        let max_zoom = self.keyframable_params.read().cached_max_zoom.clone();
+        let amount = self.keyframable_params.read().cached_stab_amount.clone();
+        if background {
+            self.setup_job = Some(BackgroundJob::spawn(&key, &self.render_trigger, move || {
+                Self::set_up_manager(&stab, disable_stretch, overview, out_size, interpolation, use_gyroflows_keyframes, &max_zoom, &amount);
+                stab
+            }));
+            return Ok(None);
+        }
+        Self::set_up_manager(&stab, disable_stretch, overview, out_size, interpolation, use_gyroflows_keyframes, &max_zoom, &amount);
+
+        Ok(Some(self.insert_manager(&key, stab)))
+    }
+
+    // Prepares the manager for rendering and computes the smoothing, this is the slow part after loading
+    fn set_up_manager(stab: &StabilizationManager, disable_stretch: bool, overview: bool, out_size: (usize, usize), interpolation: Interpolation, use_gyroflows_keyframes: bool, max_zoom: &[(i64, f64)], amount: &[(i64, f64)]) {
+        if disable_stretch {
+            stab.disable_lens_stretch(true);
+        }
+
+        stab.set_fov_overview(overview);
+
+        {
+            let mut params = stab.params.write();
+            params.framebuffer_inverted = true;
+        }
+
+        stab.init_size();
+        stab.set_output_size(out_size.0, out_size.1);
+
+        {
+            let mut stab = stab.stabilization.write();
+            stab.share_wgpu_instances = true;
+            stab.interpolation = interpolation;
+        }
+
+        stab.invalidate_smoothing();
+        Self::recompute_smoothness(stab, amount, max_zoom);
+        let inverse = !(use_gyroflows_keyframes && stab.keyframes.read().is_keyframed_internally(&KeyframeType::VideoSpeed));
+        stab.params.write().calculate_ramped_timestamps(&stab.keyframes.read(), inverse, inverse);
+    }
+
+    // Blends the smoothed orientation with the raw one by the `StabilizationAmount` param (in %), so 0% keeps the original camera motion.
+    // It has to be applied after each smoothing, returns whether anything was blended
+    fn blend_stabilization(stab: &StabilizationManager, amount: &[(i64, f64)]) -> bool {
+        if amount.iter().all(|x| x.1 >= 100.0) { return false; }
+        let mut gyro = stab.gyro.write();
+        let gyro = &mut *gyro;
+        for (timestamp_us, smoothed) in gyro.smoothed_quaternions.iter_mut() {
+            if let Some(raw) = gyro.quaternions.get(timestamp_us) {
+                let t = (KeyframableParams::curve_value_at(amount, *timestamp_us) / 100.0).clamp(0.0, 1.0);
+                *smoothed = raw.slerp(smoothed, t);
+            }
+        }
+        true
+    }
+
+    // Every smoothing is computed here, so it's always blended by the stabilization amount and followed by the zoom.
+    // The smoothing is changed in place, so it's only used on managers of this instance (set up by it, or after `detach_shared`)
+    fn recompute_smoothness(stab: &StabilizationManager, amount: &[(i64, f64)], max_zoom: &[(i64, f64)]) {
+        stab.recompute_smoothness();
+        Self::blend_stabilization(stab, amount);
+        Self::recompute_zoom(stab, max_zoom);
+    }
+    fn recompute_zoom(stab: &StabilizationManager, max_zoom: &[(i64, f64)]) {
+        stab.recompute_adaptive_zoom();
+        Self::limit_zoom(stab, max_zoom);
+        stab.recompute_undistortion();
+    }
+
+    // Adds the `SyncOffset` param (in ms) to the sync offsets of the project
+    fn apply_sync_offset(stab: &StabilizationManager, project_offsets: &[(i64, f64)], sync_offset: &[(i64, f64)]) {
+        let mut gyro = stab.gyro.write();
+        gyro.clear_offsets();
+        if project_offsets.is_empty() && sync_offset.iter().all(|x| x.1 == 0.0) { return; }
+
+        let mut timestamps = project_offsets.iter().chain(sync_offset).map(|x| x.0).collect::<Vec<_>>();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+        for timestamp_us in timestamps {
+            let offset = KeyframableParams::curve_value_at(project_offsets, timestamp_us) + KeyframableParams::curve_value_at(sync_offset, timestamp_us);
+            gyro.set_offset(timestamp_us, offset);
+        }
+    }
+
+    // Clamps the zoom computed by adaptive zoom to the `MaxZoom` param (in %, below 100% there's no limit).
+    // Only called by `recompute_zoom`, so it's applied once after every adaptive zoom
+    fn limit_zoom(stab: &StabilizationManager, max_zoom: &[(i64, f64)]) {
+        if !max_zoom.iter().any(|x| x.1 >= 100.0) { return; }
+        let mut params = stab.params.write();
+        let fps = params.fps.max(1.0);
+        for (i, fov) in params.fovs.iter_mut().enumerate() {
+            let max = KeyframableParams::curve_value_at(max_zoom, (i as f64 / fps * 1_000_000.0).round() as i64);
+            if max >= 100.0 && *fov < 100.0 / max {
+                *fov = 100.0 / max;
+            }
+        }
+    }
+
+    // Number of frames where adaptive zoom hit the `MaxZoom` limit, it's cached until the manager or the params change.
+    // The clamped frames are at the limit, compared with a relative tolerance instead of an exact match
+    fn zoom_limited_frames(&mut self, stab: &Arc<StabilizationManager>) -> usize {
+        let (hash, max_zoom) = {
+            let kparams = self.keyframable_params.read();
+            (kparams.cached_hash, kparams.cached_max_zoom.clone())
+        };
+        let id = (Arc::as_ptr(stab) as usize, hash);
+        if let Some((cached_id, count)) = self.zoom_limited {
+            if cached_id == id { return count; }
+        }
+        let params = stab.params.read();
+        let fps = params.fps.max(1.0);
+        let count = params.fovs.iter().enumerate().filter(|(i, fov)| {
+            let max = KeyframableParams::curve_value_at(&max_zoom, (*i as f64 / fps * 1_000_000.0).round() as i64);
+            max >= 100.0 && **fov <= 100.0 / max * (1.0 + ZOOM_LIMIT_TOLERANCE)
+        }).count();
+        self.zoom_limited = Some((id, count));
+        count
+    }
+
+    fn insert_manager(&mut self, key: &str, stab: StabilizationManager) -> Arc<StabilizationManager> {
+        let stab = Arc::new(stab);
+        // Insert to static global cache
+        MANAGER_CACHE.lock().insert(key.to_owned(), stab.clone());
+        // Cache it in this instance as well
+        self.cache_locally(key, &stab);
+        stab
+    }
+
+    // Loads the project or video file into a manager shared by all instances using the same data.
+    // It's not set up for rendering, instances make their own copy with `derive_manager`.
+    // In the background mode, the loading runs in a separate thread and None is returned until it's done
+    fn loaded_project(&mut self, path: &str, loading_pending_video_file: bool, background: bool) -> Result<Option<Arc<StabilizationManager>>> {
+        let key = self.project_key(path)?;
+        if let Some(stab) = MANAGER_CACHE.lock().get(&key) {
+            return Ok(Some(stab));
+        }
+
+        let loaded = if let Some(loaded) = BackgroundJob::take_result(&mut self.project_job, &key, !background) {
+            loaded
+        } else if BackgroundJob::is_running(&self.project_job, &key) {
+            return Ok(None);
+        } else {
+            let source = self.project_source(path, loading_pending_video_file)?;
+            if background {
+                self.project_job = Some(BackgroundJob::spawn(&key, &self.render_trigger, move || source.load()));
+                return Ok(None);
+            }
+            source.load()
+        };
+
+        for warning in &loaded.warnings {
+            log::warn!("{path}: {warning}");
+        }
+        self.load_warnings = loaded.warnings;
+        self.failed_project_key = loaded.stab.is_err().then(|| key.clone());
+        match loaded.stab {
+            Ok(stab) => {
+                if loaded.pending_video_file && !path.ends_with(".gyroflow") && !stab.gyro.read().file_metadata.read().has_accurate_timestamps {
+                    self.open_gyroflow();
+                }
+                let stab = Arc::new(stab);
+                MANAGER_CACHE.lock().insert(key, stab.clone());
+                Ok(Some(stab))
+            },
+            Err(LoadError::Project(e)) => {
+                log::error!("load_gyro_data error: {}", &e);
+                self.update_loaded_state(false);
+                Err(Error::UnknownError)
+            },
+            Err(LoadError::FileInfo(e)) => {
+                log::error!("An error occured: {e}");
+                self.update_loaded_state(false);
+                self.param_status.set_label("Failed to load file info!")?;
+                self.param_status.set_hint(&format!("Error loading {path}: {e}."))?;
+                if loaded.pending_video_file {
+                    self.open_gyroflow();
+                }
+                Err(Error::UnknownError)
+            }
+        }
+    }
+
+    // Collects everything needed to load the project, so it can be done outside of the render action
+    fn project_source(&self, path: &str, loading_pending_video_file: bool) -> Result<ProjectSource> {
+        // All managers share the bundled database, so the profiles are loaded only once
+        let stab = StabilizationManager { lens_profile_db: lens_profiles::shared_database(), ..Default::default() };
+        let include_project_data = self.param_include_project_data.get_value()?;
+        Ok(ProjectSource {
+            stab,
+            path:               path.to_owned(),
+            embedded_lens:      self.param_embedded_lens.get_value()?,
+            embedded_preset:    self.param_embedded_preset.get_value()?,
+            motion_data_file:   self.param_motion_data_file.get_value()?,
+            project_data:       if path.ends_with(".gyroflow") && !include_project_data { String::new() } else { self.param_project_data.get_value()? },
+            autosync_offsets:   self.param_autosync_offsets.get_value()?,
+            pending_video_file: loading_pending_video_file,
+        })
+    }
+
+    // Identifies the loaded data: the file (by its size and modification time) and everything embedded in the plugin that's loaded along with it
+    fn project_key(&self, path: &str) -> Result<String> {
+        use std::hash::{ Hash, Hasher };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.hash(&mut hasher);
+        let file_md = std::fs::metadata(path).ok();
+        if let Some(md) = &file_md {
+            md.len().hash(&mut hasher);
+            md.modified().ok().hash(&mut hasher);
+        }
+        self.param_embedded_lens.get_value()?.hash(&mut hasher);
+        self.param_embedded_preset.get_value()?.hash(&mut hasher);
+        self.param_autosync_offsets.get_value()?.hash(&mut hasher);
+        let motion_data_file = self.param_motion_data_file.get_value()?;
+        if !motion_data_file.is_empty() {
+            motion_data_file.hash(&mut hasher);
+            if let Ok(md) = std::fs::metadata(&motion_data_file) {
+                md.len().hash(&mut hasher);
+                md.modified().ok().hash(&mut hasher);
+            }
+        }
+        // Embedded project data is used instead of the file in these cases
+        let uses_embedded_data = if path.ends_with(".gyroflow") { self.param_include_project_data.get_value()? } else { file_md.is_none() };
+        if uses_embedded_data {
+            self.param_project_data.get_value()?.hash(&mut hasher);
+        }
+        Ok(format!("project:{:016x}", hasher.finish()))
+    }
+
+    // Identifies the per-instance part of the manager, so instances with the same parameters can share it
+    fn params_hash(&self) -> u64 {
+        use std::hash::{ Hash, Hasher };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.keyframable_params.read().cached_hash.hash(&mut hasher);
+        self.param_embedded_lens.get_value().unwrap_or_default().hash(&mut hasher);
+        self.param_embedded_preset.get_value().unwrap_or_default().hash(&mut hasher);
+        self.param_toggle_overview.get_value().unwrap_or_default().hash(&mut hasher);
+        self.param_per_axis.get_value().unwrap_or_default().hash(&mut hasher);
+        self.param_zoom_mode.get_value().unwrap_or_default().hash(&mut hasher);
+        self.param_horizon_lock_mode.get_value().unwrap_or_default().hash(&mut hasher);
+        for (_, handle) in &self.smoothing_params {
+            handle.get_value().unwrap_or_default().to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    fn embed_project_data(&self, path: &str, stab: &StabilizationManager) -> Result<()> {
+        let include = self.param_include_project_data.get_value()?;
+        if path.ends_with(".gyroflow") {
+            if !include {
+                self.param_project_data.set_value("".to_string())?;
+            } else if self.param_project_data.get_value()?.is_empty() {
+                if let Ok(data) = std::fs::read_to_string(path) {
+                    self.param_project_data.set_value(data)?;
+                }
+            }
+        } else if include {
+            if let Ok(data) = stab.export_gyroflow_data(gyroflow_core::GyroflowProjectType::WithGyroData, "{}", None) {
+                self.param_project_data.set_value(data)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Values are applied from the project when it's loaded next. Keyframes of `SyncOffset` would be lost, so they're reset only if the user confirms it
+    fn request_reload_values(&mut self, ask: bool) {
+        self.reload_values_from_project = true;
+        self.reset_sync_offset = self.keyframable_params.read().sync_offset.get_num_keys().unwrap_or_default() == 0 || (ask && rfd::MessageDialog::new()
+            .set_title("Reload project")
+            .set_description("Sync offset has keyframes, reloading the values from the project will delete them. Do you want to delete them?")
+            .set_level(rfd::MessageLevel::Warning)
+            .set_buttons(rfd::MessageButtons::YesNo)
+            .show() == rfd::MessageDialogResult::Yes);
+    }
+
+    // Reloads the project if the .gyroflow file was saved again since it was loaded.
+    // Values from the project are applied only if requested, so the adjusted values and keyframes are kept by default
+    pub fn check_project_changed(&mut self) -> Result<()> {
+        let path = self.param_project_path.get_value()?;
+        if !self.param_auto_reload.get_value()? || !path.ends_with(".gyroflow") {
+            self.project_watcher = None;
+            return Ok(());
+        }
+        let Some(watcher) = self.project_watcher.as_ref().filter(|x| x.path == path) else {
+            self.project_watcher = Some(ProjectWatcher::watch(&path, &self.render_trigger));
+            return Ok(());
+        };
+        if watcher.take_changed() {
+            if self.param_auto_reload_values.get_value()? {
+                self.request_reload_values(false);
+            }
+            // Embedded data would be used instead of the changed file, it's embedded again after loading
+            if self.param_include_project_data.get_value()? {
+                self.param_project_data.set_value("".to_string())?;
+            }
+            // The project was synced again in Gyroflow, the offsets from the autosync of the plugin don't apply anymore
+            if self.project_watcher.as_mut().is_some_and(|x| x.take_offsets_changed()) {
+                self.param_autosync_offsets.set_value("".to_string())?;
+            }
+            self.clear_stab();
+            // Managers of the other instances using this file are outdated too
+            MANAGER_CACHE.lock().remove_path(&path);
+        }
+        Ok(())
+    }
+
+    // Writes the values and keyframes from the plugin to the .gyroflow project, so it can be opened in Gyroflow with the same settings.
+    // Video files get a new project, saved where the user picks, next to the video by default.
+    // The project is exported from the loaded one with the plugin values applied, the managers set up for rendering have render-only state
+    pub fn save_to_project(&mut self) -> Result<()> {
+        let path = self.param_project_path.get_value()?;
+        let Some(loaded) = self.loaded_project(&path, false, false)? else {
+            rfd::MessageDialog::new()
+                .set_description("The project is not loaded yet.")
+                .show();
+            return Ok(());
+        };
+        let out_path = if path.ends_with(".gyroflow") {
+            path.clone()
+        } else {
+            // The save dialog asks before overwriting an existing project
+            let video = std::path::Path::new(&path);
+            let mut d = rfd::FileDialog::new()
+                .add_filter("Gyroflow project files", &["gyroflow"])
+                .set_file_name(video.with_extension("gyroflow").file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default());
+            if let Some(dir) = video.parent() {
+                d = d.set_directory(dir);
+            }
+            match d.save_file() {
+                Some(d) => d.display().to_string(),
+                None => return Ok(())
+            }
+        };
+        let stab = derive_manager(&loaded);
+        let project_offsets = loaded.gyro.read().get_offsets().iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        Self::apply_sync_offset(&stab, &project_offsets, &self.keyframable_params.read().cached_sync_offset);
+        let fps = self.fps.max(1.0);
+        let zoom_window = self.zoom_window()?;
+        {
+            let kparams = self.keyframable_params.read();
+            let use_gyroflows_keyframes = kparams.use_gyroflows_keyframes.get_value()?;
+            let algorithm = kparams.smoothing_algorithm.get_value()? as usize;
+            stab.smoothing.write().set_current(algorithm);
+            let mut keyframes = stab.keyframes.write();
+            macro_rules! save_key {
+                ($typ:expr, $param:expr, $scale:expr, $set_value:expr) => {
+                    if $param.get_num_keys().unwrap_or_default() > 0 {
+                        keyframes.clear_type(&$typ);
+                        for (time, v) in KeyframableParams::sample_curve(&$param) {
+                            keyframes.set(&$typ, (time / fps * 1_000_000.0).round() as i64, v / $scale);
                         }
-                        data
-                    } else {
-                        "".to_string()
+                    } else if !(use_gyroflows_keyframes && keyframes.is_keyframed_internally(&$typ)) {
+                        keyframes.clear_type(&$typ);
+                        ($set_value)($param.get_value()? / $scale);
                     }
                 };
-                let mut is_preset = false;
-                stab.import_gyroflow_data(project_data.as_bytes(), true, Some(&filesystem::path_to_url(&path)), |_|(), Arc::new(AtomicBool::new(false)), &mut is_preset, true).map_err(|e| {
-                    log::error!("load_gyro_data error: {}", &e);
-                    self.update_loaded_state(false);
-                    Error::UnknownError
-                })?;
+            }
+            save_key!(KeyframeType::Fov,                      kparams.fov,                      1.0,   |v| stab.params.write().fov = v);
+            stab.params.write().adaptive_zoom_window = zoom_window;
+            if self.param_zoom_mode.get_value()? == ZOOM_DYNAMIC {
+                save_key!(KeyframeType::ZoomingSpeed,         kparams.zoom_window,              1.0,   |v| stab.params.write().adaptive_zoom_window = v);
+            }
+            save_key!(KeyframeType::SmoothingParamSmoothness, kparams.smoothness,               1.0,   |v| stab.smoothing.write().current_mut().set_parameter("smoothness", v));
+            if smoothing_params::has_parameter(&stab.smoothing.read(), "per_axis") {
+                stab.smoothing.write().current_mut().set_parameter("per_axis", if self.param_per_axis.get_value()? { 1.0 } else { 0.0 });
+                save_key!(KeyframeType::SmoothingParamPitch, kparams.smoothness_pitch, 1.0, |v| stab.smoothing.write().current_mut().set_parameter("smoothness_pitch", v));
+                save_key!(KeyframeType::SmoothingParamYaw,   kparams.smoothness_yaw,   1.0, |v| stab.smoothing.write().current_mut().set_parameter("smoothness_yaw", v));
+                save_key!(KeyframeType::SmoothingParamRoll,  kparams.smoothness_roll,  1.0, |v| stab.smoothing.write().current_mut().set_parameter("smoothness_roll", v));
+            }
+            save_key!(KeyframeType::LensCorrectionStrength,   kparams.lens_correction_strength, 100.0, |v| stab.params.write().lens_correction_amount = v);
+            save_key!(KeyframeType::LockHorizonAmount,        kparams.horizon_lock_amount,      1.0,   |v| {
+                let mut smooth = stab.smoothing.write();
+                smooth.horizon_lock.lock_enabled = v > 0.0;
+                smooth.horizon_lock.horizonlockpercent = v;
+            });
+            save_key!(KeyframeType::LockHorizonRoll,          kparams.horizon_lock_roll,        1.0,   |v| stab.smoothing.write().horizon_lock.horizonroll = v);
+            stab.smoothing.write().horizon_lock.lock_pitch = self.param_horizon_lock_mode.get_value()? == HORIZON_LOCK_ROLL_PITCH;
+            save_key!(KeyframeType::LockHorizonPitch,         kparams.horizon_lock_pitch,       1.0,   |v| stab.smoothing.write().horizon_lock.horizonpitch = v);
+            save_key!(KeyframeType::VideoSpeed,               kparams.video_speed,              100.0, |v| stab.params.write().video_speed = v);
+            save_key!(KeyframeType::VideoRotation,            kparams.rotation,                 1.0,   |v| stab.params.write().video_rotation = v);
+            save_key!(KeyframeType::ZoomingCenterX,           kparams.positionx,                100.0, |v| stab.params.write().adaptive_zoom_center_offset.0 = v);
+            save_key!(KeyframeType::ZoomingCenterY,           kparams.positiony,                100.0, |v| stab.params.write().adaptive_zoom_center_offset.1 = v);
+            for (p, handle) in self.smoothing_params.iter().filter(|(p, _)| p.algorithm == algorithm) {
+                match (&p.keyframe, handle) {
+                    (Some(typ), SmoothingParamHandle::Double(param)) => { save_key!(*typ, param, 1.0, |v| stab.smoothing.write().current_mut().set_parameter(&p.name, v)); },
+                    _ => stab.smoothing.write().current_mut().set_parameter(&p.name, handle.get_value()?)
+                }
             }
 
-            let loaded = {
-                stab.params.write().calculate_ramped_timestamps(&stab.keyframes.read(), false, true);
-                let params = stab.params.read();
-                self.original_video_size = params.size;
-                self.original_output_size = params.output_size;
-                self.num_frames = params.frame_count;
-                self.fps = params.fps;
-                let loaded = params.duration_ms > 0.0;
-                if loaded && self.reload_values_from_project {
-                    self.reload_values_from_project = false;
-                    let smooth = stab.smoothing.read();
-                    let smoothness = smooth.current().get_parameter("smoothness");
+            // Speed keyframes are on the output timeline in Gyroflow, and on the source timeline in the plugin (see `set_keys!`).
+            // The mapping depends on the keyframes themselves, so refine it a few times
+            if kparams.video_speed.get_num_keys().unwrap_or_default() > 0 {
+                let source_keys = keyframes.get_keyframes(&KeyframeType::VideoSpeed).map(|x| x.iter().map(|(ts, v)| (*ts, v.value)).collect::<Vec<_>>()).unwrap_or_default();
+                for _ in 0..3 {
+                    stab.params.write().calculate_ramped_timestamps(&keyframes, false, true);
+                    let params = stab.params.read();
+                    keyframes.clear_type(&KeyframeType::VideoSpeed);
+                    for (ts, v) in &source_keys {
+                        keyframes.set(&KeyframeType::VideoSpeed, Self::ramped_timestamp(&params, *ts), *v);
+                    }
+                }
+            }
+        }
 
+        let existing = std::fs::read_to_string(&out_path).unwrap_or_default();
+        let result = stab.export_gyroflow_data(gyroflow_core::GyroflowProjectType::WithGyroData, "{}", None)
+            .map_err(|e| format!("{e:?}"))
+            .map(|data| Self::keep_other_sections(&existing, data))
+            .and_then(|data| std::fs::write(&out_path, &data).map(|_| data).map_err(|e| format!("{e:?}")));
+        match result {
+            Ok(data) => {
+                log::info!("Saved project to {out_path}");
+                if path.ends_with(".gyroflow") && self.param_include_project_data.get_value()? {
+                    self.param_project_data.set_value(data)?;
+                }
+                // The sync offset and the autosync result are included in the saved offsets, so they're reset and the project is loaded again
+                if path.ends_with(".gyroflow") {
+                    self.param_autosync_offsets.set_value("".to_string())?;
                     let kparams = self.keyframable_params.read();
-                    kparams.fov.set_value(params.fov)?;
-                    kparams.smoothness.set_value(smoothness)?;
-                    kparams.lens_correction_strength.set_value((params.lens_correction_amount * 100.0).min(100.0))?;
-                    kparams.horizon_lock_amount.set_value(if smooth.horizon_lock.lock_enabled { smooth.horizon_lock.horizonlockpercent } else { 0.0 })?;
-                    kparams.horizon_lock_roll.set_value(if smooth.horizon_lock.lock_enabled { smooth.horizon_lock.horizonroll } else { 0.0 })?;
-                    kparams.video_speed.set_value(params.video_speed * 100.0)?;
-                    kparams.positionx.set_value(params.adaptive_zoom_center_offset.0 * 100.0)?;
-                    kparams.positiony.set_value(params.adaptive_zoom_center_offset.1 * 100.0)?;
-                    kparams.rotation.set_value(params.video_rotation)?;
-
-                    let keyframes = stab.keyframes.read();
-                    let all_keys = keyframes.get_all_keys();
-                    kparams.use_gyroflows_keyframes.set_value(!all_keys.is_empty())?;
-                    for k in all_keys {
-                        if let Some(keys) = keyframes.get_keyframes(k) {
-                            if !keys.is_empty() {
-                                macro_rules! set_keys {
-                                    ($name:expr, $scale:expr) => {
-                                        $name.delete_all_keys()?;
-                                        for (ts, v) in keys {
-                                            let ts = if k == &KeyframeType::VideoSpeed { params.get_source_timestamp_at_ramped_timestamp(*ts) } else { *ts };
-                                            let time = (((ts as f64 / 1000.0) * params.fps) / 1000.0).round();
-                                            $name.set_value_at_time(time, v.value * $scale)?;
-                                        }
-                                    };
-                                }
-                                match k {
-                                    KeyframeType::Fov                      => { set_keys!(kparams.fov,                      1.0); },
-                                    KeyframeType::SmoothingParamSmoothness => { set_keys!(kparams.smoothness,               1.0); },
-                                    KeyframeType::LensCorrectionStrength   => { set_keys!(kparams.lens_correction_strength, 100.0); },
-                                    KeyframeType::LockHorizonAmount        => { set_keys!(kparams.horizon_lock_amount,      1.0); },
-                                    KeyframeType::LockHorizonRoll          => { set_keys!(kparams.horizon_lock_roll,        1.0); },
-                                    KeyframeType::VideoSpeed               => { set_keys!(kparams.video_speed,              100.0); },
-                                    KeyframeType::VideoRotation            => { set_keys!(kparams.rotation,                 1.0); },
-                                    KeyframeType::ZoomingCenterX           => { set_keys!(kparams.positionx,                100.0); },
-                                    KeyframeType::ZoomingCenterY           => { set_keys!(kparams.positiony,                100.0); },
-                                    _ => { }
-                                }
-                            }
-                        }
-                    }
+                    kparams.sync_offset.delete_all_keys()?;
+                    kparams.sync_offset.set_value(0.0)?;
+                    drop(kparams);
+                    self.keyframable_params.write().cache_keyframes(self.fps.max(1.0));
+                    self.clear_stab();
+                    MANAGER_CACHE.lock().remove_path(&path);
                 }
-                self.keyframable_params.write().cache_keyframes(self.num_frames, self.fps.max(1.0));
-                loaded
-            };
+            },
+            Err(e) => {
+                log::error!("Failed to save project {out_path}: {e}");
+                rfd::MessageDialog::new()
+                    .set_description(&format!("Failed to save project {out_path}: {e}"))
+                    .show();
+            }
+        }
+        Ok(())
+    }
 
-            self.update_loaded_state(loaded);
+    // Sections of the existing project which aren't exported by the plugin (eg. the render settings in `output`) are kept as they were
+    fn keep_other_sections(existing: &str, exported: String) -> String {
+        let (Ok(serde_json::Value::Object(existing)), Ok(serde_json::Value::Object(mut merged))) = (serde_json::from_str(existing), serde_json::from_str::<serde_json::Value>(&exported)) else {
+            return exported;
+        };
+        for (key, value) in existing {
+            merged.entry(key).or_insert(value);
+        }
+        serde_json::to_string_pretty(&serde_json::Value::Object(merged)).unwrap_or(exported)
+    }
 
-            if disable_stretch {
-                stab.disable_lens_stretch(true);
+    // Computes the smallest zoom which hides all the areas outside of the frame at the current stabilization, and writes it to the `FOV` param.
+    // The FOV replaces the adaptive zoom, so zooming is disabled. Per segment, each segment gets its own value as keyframes
+    pub fn analyze_crop(&mut self) -> Result<()> {
+        let Some(loaded) = self.managers().into_iter().next() else {
+            rfd::MessageDialog::new()
+                .set_description("The project is not loaded yet.")
+                .show();
+            return Ok(());
+        };
+        if self.keyframable_params.read().fov.get_num_keys().unwrap_or_default() > 0 {
+            let result = rfd::MessageDialog::new()
+                .set_title("Analyze crop")
+                .set_description("The keyframes of FOV will be replaced by the analyzed crop. Do you want to continue?")
+                .set_level(rfd::MessageLevel::Warning)
+                .set_buttons(rfd::MessageButtons::YesNo)
+                .show();
+            if result != rfd::MessageDialogResult::Yes {
+                return Ok(());
             }
+        }
+        let per_segment = self.param_analyze_crop_mode.get_value()? == CROP_PER_SEGMENT;
+        let fps = self.fps.max(1.0);
+
+        let stab = derive_manager(&loaded);
+        // Static zoom gives the worst case of the whole clip, a window of one frame gives the zoom needed by each frame
+        stab.params.write().adaptive_zoom_window = if per_segment { 1.0 / fps } else { -1.0 };
+        stab.recompute_adaptive_zoom();
+
+        // FOV needed at every frame of the clip. The frames of the video are found from the clip time like in the render,
+        // so the keys are set at the same times they're read at.
+        // The FOV scales the adaptive zoom, so its current value is kept as a multiplier. Without adaptive zoom it's the crop itself
+        // (eg. from an earlier analysis), so it's replaced
+        let zoom_enabled = self.zoom_window()? != 0.0;
+        let (video_fps, num_fovs) = {
+            let params = stab.params.read();
+            (params.fps, params.fovs.len())
+        };
+        let last_frame = self.source_clip.get_frame_range().map(|x| x.max).unwrap_or(num_fovs as f64 - 1.0).round().max(0.0) as usize;
+        let mut needed = Vec::with_capacity(last_frame + 1);
+        for frame in 0..=last_frame {
+            let time = frame as f64;
+            let timestamp_us = self.timestamp_at(&stab, time);
+            let params = stab.params.read();
+            let source_frame = (params.get_source_timestamp_at_ramped_timestamp(timestamp_us) as f64 * video_fps / 1_000_000.0).round().max(0.0) as usize;
+            let Some(fov) = params.fovs.get(source_frame.min(num_fovs.saturating_sub(1))).copied() else { break; };
+            let multiplier = if zoom_enabled { self.keyframable_params.read().fov.get_value_at_time(time)? } else { 1.0 };
+            needed.push((time, fov * multiplier));
+        }
 
-            stab.set_fov_overview(self.param_toggle_overview.get_value()?);
+        let kparams = self.keyframable_params.read();
+        let fov_param = &kparams.fov;
+        fov_param.delete_all_keys()?;
+        if per_segment && !needed.is_empty() {
+            let segment_frames = ((self.param_analyze_segment.get_value()? * fps).round() as usize).max(1);
+            for segment in needed.chunks(segment_frames) {
+                let fov = segment.iter().map(|x| x.1).fold(f64::MAX, f64::min);
+                // Keys at both ends of the segment, so the interpolation between segments doesn't show the borders
+                for (time, _) in [segment[0], segment[segment.len() - 1]] {
+                    fov_param.set_value_at_time(time, fov)?;
+                }
+            }
+        } else {
+            fov_param.set_value(needed.iter().map(|x| x.1).reduce(f64::min).unwrap_or(1.0))?;
+        }
+        drop(kparams);
+        self.param_zoom_mode.set_value(ZOOM_DISABLED)?;
+
+        self.update_loaded_state(true);
+        self.keyframable_params.write().cache_keyframes(fps);
+        self.detach_shared();
+        let max_zoom = self.keyframable_params.read().cached_max_zoom.clone();
+        for v in self.managers() {
+            self.apply_zoom(&v)?;
+            Self::recompute_zoom(&v, &max_zoom);
+        }
+        self.rekey_managers();
+        Ok(())
+    }
 
-            {
-                let mut params = stab.params.write();
-                params.framebuffer_inverted = true;
+    // Syncs the gyro data with optical flow of the source clip. The frames around the sync points are read here and synced in the background
+    pub fn start_autosync(&mut self) -> Result<()> {
+        let Some(loaded) = self.managers().into_iter().next() else {
+            rfd::MessageDialog::new()
+                .set_description("The project is not loaded yet.")
+                .show();
+            return Ok(());
+        };
+        if self.autosync_job.is_some() { return Ok(()); }
+        self.autosync_error = None;
+
+        let stab = derive_manager(&loaded);
+        // Offsets from the `SyncOffset` param would be included in the result
+        stab.gyro.write().clear_offsets();
+        let (fps, num_frames) = {
+            let params = stab.params.read();
+            (params.fps.max(1.0), params.frame_count)
+        };
+        let sync = match autosync::new_process(&stab) {
+            Ok(sync) => sync,
+            Err(e) => {
+                log::error!("Autosync: {e}");
+                self.autosync_error = Some(e);
+                return Ok(());
             }
-
-
-            stab.init_size();
-            stab.set_output_size(out_size.0, out_size.1);
-
-            {
-                let mut stab = stab.stabilization.write();
-                stab.share_wgpu_instances = true;
-                stab.interpolation = gyroflow_core::stabilization::Interpolation::Lanczos4;
+        };
+        // Only the optical flow is done in the background
+        let frames = match autosync::read_frames(&self.source_clip, &autosync::frames_to_read(&sync, fps, num_frames), fps) {
+            Ok(frames) => frames,
+            Err(e) => {
+                log::error!("Autosync: {e}");
+                self.autosync_error = Some(e);
+                return Ok(());
             }
+        };
 
-            self.set_keyframe_provider(&stab);
+        let path = self.param_project_path.get_value()?;
+        log::info!("Autosync of {path} started with {} frames", frames.len());
+        self.autosync_job = Some(BackgroundJob::spawn(&path, &self.render_trigger, move || autosync::run(sync, frames)));
+        Ok(())
+    }
 
-            stab.invalidate_smoothing();
-            stab.recompute_blocking();
-            let inverse = !(self.keyframable_params.read().use_gyroflows_keyframes.get_value()? && stab.keyframes.read().is_keyframed_internally(&KeyframeType::VideoSpeed));
-            stab.params.write().calculate_ramped_timestamps(&stab.keyframes.read(), inverse, inverse);
+    // Applies the offsets found by autosync to the loaded project and stores them in the plugin, so they're kept when it's loaded again
+    pub fn check_autosync(&mut self) -> Result<()> {
+        let Some(key) = self.autosync_job.as_ref().map(|x| x.key.clone()) else { return Ok(()); };
+        let Some(result) = BackgroundJob::take_result(&mut self.autosync_job, &key, false) else { return Ok(()); };
+        let path = self.param_project_path.get_value()?;
+        if key != path { return Ok(()); }
+        let offsets = match result {
+            Ok(offsets) => offsets,
+            Err(e) => {
+                log::error!("Autosync failed: {e}");
+                self.autosync_error = Some(e);
+                return Ok(());
+            }
+        };
+        log::info!("Autosync found offsets: {offsets:?}");
+
+        let project_key = self.project_key(&path)?;
+        let Some(loaded) = MANAGER_CACHE.lock().get(&project_key) else { return Ok(()); };
+        // Other instances may use the loaded project without these offsets, so the synced one is a copy
+        let base = Arc::new(derive_manager(&loaded));
+        {
+            let mut gyro = base.gyro.write();
+            gyro.clear_offsets();
+            for (timestamp_us, offset) in &offsets {
+                gyro.set_offset(*timestamp_us, *offset);
+            }
+        }
+        let offsets = offsets.iter().map(|(timestamp_us, offset)| (timestamp_us.to_string(), serde_json::Value::from(*offset))).collect::<serde_json::Map<_, _>>();
+        self.param_autosync_offsets.set_value(serde_json::json!({ "path": path, "offsets": offsets }).to_string())?;
+        if self.param_include_project_data.get_value()? {
+            if let Ok(data) = base.export_gyroflow_data(gyroflow_core::GyroflowProjectType::WithGyroData, "{}", None) {
+                self.param_project_data.set_value(data)?;
+            }
+        }
+        // The stored offsets and the embedded data are part of the key, the synced project doesn't have to be loaded again
+        MANAGER_CACHE.lock().insert(self.project_key(&path)?, base);
 
-            let stab = Arc::new(stab);
-            // Insert to static global cache
-            MANAGER_CACHE.lock().put(key.to_owned(), stab.clone());
-            // Cache it in this instance as well
-            self.gyrodata.put(key.to_owned(), stab.clone());
+        self.clear_stab();
+        MANAGER_CACHE.lock().remove_path(&path);
+        Ok(())
+    }
 
-            stab
+    // Selects the lens profile from the search results, and embeds it so it's loaded with the video
+    fn select_lens_profile(&mut self, index: usize) -> Result<()> {
+        let Some((name, id)) = self.lens_results.get(index).cloned() else {
+            self.param_lens_profile_name.set_value("No matching lens profiles".to_owned())?;
+            return Ok(());
         };
+        self.lens_result_index = Some(index);
+        let Some(json) = lens_profiles::profile_json(&id) else {
+            log::error!("Failed to read lens profile {id}");
+            return Ok(());
+        };
+        self.param_lens_profile_name.set_value(format!("{name} ({}/{})", index + 1, self.lens_results.len()))?;
+        self.param_embedded_lens.set_value(json)?;
+        self.clear_stab();
+        Ok(())
+    }
 
-        Ok(stab)
+    // Inverse of `get_source_timestamp_at_ramped_timestamp`, which is monotonic for positive speeds
+    fn ramped_timestamp(params: &gyroflow_core::stabilization_params::StabilizationParams, source_timestamp_us: i64) -> i64 {
+        let mut hi = source_timestamp_us.max(1);
+        while params.get_source_timestamp_at_ramped_timestamp(hi) < source_timestamp_us && hi < i64::MAX / 4 {
+            hi *= 2;
+        }
+        let mut lo = 0;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if params.get_source_timestamp_at_ramped_timestamp(mid) < source_timestamp_us { lo = mid + 1; } else { hi = mid; }
+        }
+        lo
     }
 
     pub fn check_pending_file_info(&mut self) -> Result<bool> { // -> is_video_file
@@ -410,6 +1447,154 @@ impl InstanceData {
         Ok(false)
     }
 
+    // Ratio of the project timestamps to the timeline ones, when the clip was retimed in the host or its frame rate doesn't match
+    fn speed_stretch(&self, frame_count: usize, fps: f64) -> f64 {
+        let src_fps = self.source_clip.get_frame_rate().unwrap_or(fps);
+        let frame_number = (frame_count - 1) as f64;
+
+        let mut speed_stretch = 1.0;
+        if let Ok(range) = self.source_clip.get_frame_range() {
+            if range.max > 0.0 && (frame_number - range.max).abs() > 2.0 {
+                speed_stretch = ((frame_number / range.max) * 100.0).round() / 100.0;
+            }
+        }
+        speed_stretch * src_fps / fps
+    }
+
+    fn output_region_of_definition(&self, time: f64) -> Result<RectD> {
+        let mut rod = self.source_clip.get_region_of_definition(time)?;
+        if self.original_output_size != (0, 0) && !self.param_dont_draw_outside.get_value_at_time(time)? {
+            rod.x2 = self.original_output_size.0 as f64;
+            rod.y2 = self.original_output_size.1 as f64;
+        }
+        Ok(rod)
+    }
+
+    // Manager set up for the current parameters, if it's loaded already
+    fn current_manager(&self) -> Option<Arc<StabilizationManager>> {
+        let key_suffix = format!("|{:016x}", self.params_hash());
+        self.gyrodata.iter().filter(|(k, _)| k.ends_with(&key_suffix)).find_map(|(_, v)| v.upgrade())
+    }
+
+    // Timestamp of the frame at `time` of the timeline in the project, before the speed ramps
+    fn timestamp_at(&self, stab: &StabilizationManager, time: f64) -> i64 {
+        let (fps, frame_count) = {
+            let params = stab.params.read();
+            (params.fps, params.frame_count)
+        };
+        let src_fps = self.source_clip.get_frame_rate().unwrap_or(fps);
+        ((time / src_fps * 1_000_000.0) * self.speed_stretch(frame_count, fps)).round() as i64
+    }
+
+    // Whether the settings don't change the frame at `time`, so the host can use the source frame instead of rendering it
+    fn is_identity(&self, time: f64) -> Result<bool> {
+        let Some(stab) = self.current_manager() else { return Ok(false); };
+        {
+            let kparams = self.keyframable_params.read();
+            if kparams.stabilization_amount.get_value_at_time(time)? != 0.0 || kparams.lens_correction_strength.get_value_at_time(time)? != 0.0 ||
+               kparams.fov.get_value_at_time(time)? != 1.0 || kparams.rotation.get_value_at_time(time)? != 0.0 ||
+               kparams.positionx.get_value_at_time(time)? != 0.0 || kparams.positiony.get_value_at_time(time)? != 0.0 {
+                return Ok(false);
+            }
+            // Keyframes from the project override the params
+            if kparams.use_gyroflows_keyframes.get_value()? {
+                let keyframes = stab.keyframes.read();
+                let types = [KeyframeType::Fov, KeyframeType::LensCorrectionStrength, KeyframeType::VideoRotation, KeyframeType::ZoomingCenterX, KeyframeType::ZoomingCenterY];
+                if types.iter().any(|x| keyframes.is_keyframed_internally(x)) {
+                    return Ok(false);
+                }
+            }
+        }
+        if self.param_input_rotation.get_value_at_time(time).unwrap_or_default() != 0.0 {
+            return Ok(false);
+        }
+        // The overview zooms out and the input stretch of the lens resizes the frame, disabling it needs a de-stretched source
+        if self.param_toggle_overview.get_value()? || self.param_disable_stretch.get_value()? {
+            return Ok(false);
+        }
+        {
+            let lens = stab.lens.read();
+            let stretched = |x: f64| x > 0.01 && (x - 1.0).abs() > 1e-4;
+            if stretched(lens.input_horizontal_stretch) || stretched(lens.input_vertical_stretch) {
+                return Ok(false);
+            }
+        }
+        let (source_rod, out_rod) = (self.source_clip.get_region_of_definition(time)?, self.output_region_of_definition(time)?);
+        if source_rod.x1 != out_rod.x1 || source_rod.x2 != out_rod.x2 || source_rod.y1 != out_rod.y1 || source_rod.y2 != out_rod.y2 {
+            return Ok(false);
+        }
+
+        // Rolling shutter correction, adaptive zoom and speed ramps change the frame as well
+        let timestamp_us = self.timestamp_at(&stab, time);
+        let params = stab.params.read();
+        let frame = (timestamp_us as f64 * params.fps / 1_000_000.0).round().max(0.0) as usize;
+        Ok(params.frame_readout_time == 0.0 &&
+           !params.fovs.get(frame).is_some_and(|x| (x - 1.0).abs() >= 1e-4) &&
+           params.get_source_timestamp_at_ramped_timestamp(timestamp_us) == timestamp_us)
+    }
+
+    // Part of the source needed to render the output frame at `time`, in canonical coordinates.
+    // Tiles are copied from the whole rendered frame, so it's the same for every region of the output.
+    // A grid over the video is mapped through the stabilization of this frame, and the cells which land in the output are included.
+    // The whole source is needed until the manager for the current parameters is set up
+    fn source_region_of_interest(&self, time: f64) -> Result<RectD> {
+        let source_rod = self.source_clip.get_region_of_definition(time)?;
+        let Some(stab) = self.current_manager() else {
+            return Ok(source_rod);
+        };
+        let out_rod = self.output_region_of_definition(time)?;
+
+        let timestamp_us = self.timestamp_at(&stab, time);
+        let params = stab.params.read();
+        let (fps, size, output_size) = (params.fps, params.size, params.output_size);
+        if size.0 == 0 || size.1 == 0 || output_size.0 == 0 || output_size.1 == 0 {
+            return Ok(source_rod);
+        }
+        let timestamp_ms = params.get_source_timestamp_at_ramped_timestamp(timestamp_us) as f64 / 1000.0;
+        drop(params);
+
+        let compute_params = ComputeParams::from_manager(&stab);
+        let frame = (timestamp_ms * fps / 1000.0).round().max(0.0) as usize;
+        let points = tiles::grid_points(ROI_GRID, size);
+        let mapped = undistort_points_with_rolling_shutter(&points, timestamp_ms, Some(frame), &compute_params, compute_params.lens_correction_amount, true);
+
+        // The video is scaled to the center rect of the source, like in Render
+        let (src_w, src_h) = ((source_rod.x2 - source_rod.x1) as usize, (source_rod.y2 - source_rod.y1) as usize);
+        let src_rect = Self::get_center_rect(src_w, src_h, size.0 as f64 / size.1 as f64);
+
+        // Output points are top-down in the output size, the canonical coordinates are bottom-up.
+        // The output is drawn into the center of the source when it's not drawn outside of it, like in Render
+        let (out_w, out_h) = (out_rod.x2 - out_rod.x1, out_rod.y2 - out_rod.y1);
+        let out_rect = if self.param_dont_draw_outside.get_value_at_time(time)? {
+            let rect = Self::get_center_rect(src_rect.2, src_rect.3, out_w / out_h);
+            ((rect.0 + src_rect.0) as f64, (rect.1 + src_rect.1) as f64, rect.2 as f64, rect.3 as f64)
+        } else {
+            (0.0, 0.0, out_w, out_h)
+        };
+        let to_output = |p: (f32, f32)| (out_rod.x1 + out_rect.0 + p.0 as f64 / output_size.0 as f64 * out_rect.2, out_rod.y2 - out_rect.1 - p.1 as f64 / output_size.1 as f64 * out_rect.3);
+
+        // Nothing of the source is visible
+        let Some(video_rect) = tiles::visible_video_rect(&mapped, ROI_GRID, size, to_output, &out_rod) else {
+            return Ok(RectD { x1: source_rod.x1, y1: source_rod.y1, x2: source_rod.x1, y2: source_rod.y1 });
+        };
+
+        let (scale_x, scale_y) = (src_rect.2 as f64 / size.0 as f64, src_rect.3 as f64 / size.1 as f64);
+        let (left, bottom) = (source_rod.x1 + src_rect.0 as f64, source_rod.y1 + src_rect.1 as f64);
+        let mut rect = (left + video_rect.0 * scale_x, bottom + (size.1 as f64 - video_rect.3) * scale_y, left + video_rect.2 * scale_x, bottom + (size.1 as f64 - video_rect.1) * scale_y);
+        // The source is rotated around its center before the stabilization
+        let input_rotation = self.param_input_rotation.get_value_at_time(time).unwrap_or_default();
+        if input_rotation != 0.0 {
+            let center = ((source_rod.x1 + source_rod.x2) / 2.0, (source_rod.y1 + source_rod.y2) / 2.0);
+            rect = tiles::rotated_bounds(rect, center, input_rotation);
+        }
+        Ok(RectD {
+            x1: (rect.0 - ROI_MARGIN).floor().max(source_rod.x1),
+            y1: (rect.1 - ROI_MARGIN).floor().max(source_rod.y1),
+            x2: (rect.2 + ROI_MARGIN).ceil().min(source_rod.x2),
+            y2: (rect.3 + ROI_MARGIN).ceil().min(source_rod.y2),
+        })
+    }
+
     fn get_center_rect(width: usize, height: usize, org_ratio: f64) -> (usize, usize, usize, usize) {
         // If aspect ratio is different
         let new_ratio = width as f64 / height as f64;
@@ -431,19 +1616,81 @@ impl InstanceData {
         }
     }
 
+    fn cache_locally(&mut self, key: &str, stab: &Arc<StabilizationManager>) {
+        let mut lock = MANAGER_CACHE.lock();
+        if let Some((old_key, _)) = self.gyrodata.push(key.to_owned(), Arc::downgrade(stab)) {
+            if old_key != key {
+                lock.release(&old_key);
+            }
+        }
+        lock.acquire(key);
+    }
+
+    // Managers of this instance which are still in the global cache
+    fn managers(&self) -> Vec<Arc<StabilizationManager>> {
+        self.gyrodata.iter().filter_map(|(_, v)| v.upgrade()).collect()
+    }
+
     pub fn clear_stab(&mut self) {
         let local_keys = self.gyrodata.iter().map(|x| x.0.clone()).collect::<Vec<_>>();
         self.gyrodata.clear();
+        // Results of the jobs still running would be outdated
+        self.project_job = None;
+        self.setup_job = None;
 
-        // If there are no more local references, delete it from global cache
+        // If no other instance uses it, delete it from global cache so it's loaded again
         let mut lock = MANAGER_CACHE.lock();
         for key in local_keys {
-            if let Some(v) = lock.get(&key) {
-                if Arc::strong_count(v) == 1 {
-                    lock.pop(&key);
-                }
+            lock.release(&key);
+            lock.remove_unused(&key);
+        }
+    }
+
+    // Drops the managers shared with other instances, so the remaining ones can be modified in place.
+    // The dropped ones are set up again for the new parameters on the next render, from the already loaded project
+    fn detach_shared(&mut self) {
+        let mut lock = MANAGER_CACHE.lock();
+        let shared = self.gyrodata.iter().filter(|(k, _)| lock.users(k) > 1).map(|(k, _)| k.clone()).collect::<Vec<_>>();
+        for key in shared {
+            self.gyrodata.pop(&key);
+            lock.release(&key);
+        }
+    }
+
+    // Moves the managers of this instance to the keys matching the current parameters
+    fn rekey_managers(&mut self) {
+        let hash = self.params_hash();
+        let entries = self.gyrodata.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>();
+        self.gyrodata.clear();
+
+        let mut lock = MANAGER_CACHE.lock();
+        for (key, stab) in entries.into_iter().rev() {
+            lock.release(&key);
+            let Some(stab) = stab.upgrade() else { continue; };
+            let Some((prefix, _)) = key.rsplit_once('|') else { continue; };
+            let new_key = format!("{prefix}|{hash:016x}");
+            if new_key != key {
+                lock.remove_unused(&key);
             }
+            // Another instance may already have a manager for these parameters
+            let stab = match lock.get(&new_key) {
+                // The same manager, its smoothing was recomputed so it may hold more memory now
+                Some(existing) if Arc::ptr_eq(&existing, &stab) => { lock.update_footprint(&new_key); existing },
+                Some(existing) => existing,
+                None => { lock.insert(new_key.clone(), stab.clone()); stab }
+            };
+            self.gyrodata.put(new_key.clone(), Arc::downgrade(&stab));
+            lock.acquire(&new_key);
+        }
+    }
+
+    // Unlike `clear_stab`, keeps the managers in the global cache, so they can be reused when the instance is recreated
+    pub fn release_stab(&mut self) {
+        let mut lock = MANAGER_CACHE.lock();
+        for (key, _) in self.gyrodata.iter() {
+            lock.release(key);
         }
+        self.gyrodata.clear();
     }
 
     pub fn get_gyroflow_location() -> Option<String> {
@@ -531,7 +1778,25 @@ impl Execute for GyroflowPlugin {
                 let time = in_args.get_time()?;
                 let instance_data: &mut InstanceData = effect.get_instance_data()?;
 
+                // The buffer is taken directly from the image properties, ofx has no descriptors for f16, RGB and Alpha pixels.
+                // The data points to the bottom row, when the row bytes are negative the rows go down in memory,
+                // so the buffer starts at the top row which has the lowest address
+                macro_rules! cpu_buffer {
+                    ($image:expr) => {
+                        unsafe {
+                            if let BitDepth::None = $image.get_pixel_depth()? { return FAILED; }
+                            let b = $image.get_bounds()?;
+                            let (row_bytes, rows) = ($image.get_row_bytes()? as isize, (b.y2 - b.y1).max(0) as isize);
+                            let data = $image.get_data()? as *mut u8;
+                            let data = if row_bytes < 0 && rows > 0 { data.offset(row_bytes * (rows - 1)) } else { data };
+                            std::slice::from_raw_parts_mut(data, row_bytes.unsigned_abs() * rows as usize)
+                        }
+                    };
+                }
+
                 let loading_pending_video_file = instance_data.check_pending_file_info()?;
+                instance_data.check_project_changed()?;
+                instance_data.check_autosync()?;
 
                 let output_image = if in_args.get_opengl_enabled().unwrap_or_default() {
                     instance_data.output_clip.load_texture_mut(time, None)?
@@ -541,8 +1806,27 @@ impl Execute for GyroflowPlugin {
                 let output_image = output_image.borrow_mut();
 
                 let output_rect: RectI = output_image.get_region_of_definition()?;
-
-                let stab = instance_data.gyrodata(output_image.get_pixel_depth()?, output_rect, loading_pending_video_file)?;
+                let gpu = in_args.get_opengl_enabled().unwrap_or_default() || in_args.get_opencl_enabled().unwrap_or_default() ||
+                          in_args.get_metal_enabled().unwrap_or_default() || in_args.get_cuda_enabled().unwrap_or_default();
+
+                // Final renders wait for the loading, interactive ones get the source frame until it's done.
+                // GPU buffers can't be copied here, so GPU renders wait as well
+                let interactive = in_args.get_interactive_render_status().unwrap_or_default();
+                let draft = interactive || in_args.get_raw::<Int, _>("OfxImageEffectPropRenderQualityDraft\0".as_bytes()).unwrap_or_default() != 0;
+                let interpolation = instance_data.interpolation(draft)?;
+                let stab = match instance_data.gyrodata(output_image.get_pixel_depth()?, output_rect, interpolation, loading_pending_video_file, interactive && !gpu)? {
+                    Some(stab) => stab,
+                    None => {
+                        instance_data.set_loading_status()?;
+                        // Pass the source frame through, the images are placed by their bounds, so it works with tiles as well
+                        let source_image = instance_data.source_clip.get_image(time)?;
+                        let bpp = bytes_per_pixel(output_image.get_pixel_depth()?, output_image.get_components()?);
+                        let src = CpuImage { data: cpu_buffer!(source_image), stride: source_image.get_row_bytes()? as isize, bounds: source_image.get_bounds()? };
+                        let mut dst = CpuImage { data: cpu_buffer!(output_image), stride: output_image.get_row_bytes()? as isize, bounds: output_image.get_bounds()? };
+                        tiles::copy_region(&src, &mut dst, bpp);
+                        return OK;
+                    }
+                };
 
                 let params = stab.params.read();
                 let fps = params.fps;
@@ -554,38 +1838,50 @@ impl Execute for GyroflowPlugin {
                     (md.has_accurate_timestamps, !gyro.get_offsets().is_empty())
                 };
 
-                let frame_number = (params.frame_count - 1) as f64;
-
-                let mut speed_stretch = 1.0;
-                if let Ok(range) = instance_data.source_clip.get_frame_range() {
-                    if range.max > 0.0 {
-                        if (frame_number - range.max).abs() > 2.0 {
-                            speed_stretch = ((frame_number / range.max) * 100.0).round() / 100.0;
-                        }
-                    }
-                }
                 if (src_fps - fps).abs() > 0.01 {
                     instance_data.param_status.set_label("Timeline fps mismatch!")?;
                     instance_data.param_status.set_hint("Timeline frame rate doesn't match the clip frame rate!")?;
                     if instance_data.param_status.get_value()? {
                         instance_data.param_status.set_value(false)?;
                     }
+                } else if !has_accurate_timestamps && !has_offsets && instance_data.autosync_job.is_some() {
+                    instance_data.param_status.set_label("Syncing...")?;
+                    instance_data.param_status.set_hint("Autosync is running in the background")?;
+                    if instance_data.param_status.get_value()? {
+                        instance_data.param_status.set_value(false)?;
+                    }
                 } else if !has_accurate_timestamps && !has_offsets {
                     instance_data.param_status.set_label("Not synced. Open in Gyroflow")?;
-                    instance_data.param_status.set_hint("Gyro data is not synced with the video, open the video in Gyroflow and add sync points (eg. by doing autosync)")?;
+                    match &instance_data.autosync_error {
+                        Some(e) => instance_data.param_status.set_hint(&format!("Autosync failed: {e}"))?,
+                        None => instance_data.param_status.set_hint("Gyro data is not synced with the video, click Autosync or open the video in Gyroflow and add sync points")?,
+                    }
                     if instance_data.param_status.get_value()? {
                         instance_data.param_status.set_value(false)?;
                     }
                 } else {
                     instance_data.param_status.set_label("OK")?;
-                    instance_data.param_status.set_hint("OK")?;
+                    let zoom_limited = instance_data.zoom_limited_frames(&stab);
+                    let mut hint = if zoom_limited > 0 { format!("OK. Zoom was limited by Max zoom in {zoom_limited} frames") } else { "OK".to_owned() };
+                    match instance_data.auto_lens.as_deref() {
+                        Some("") => hint.push_str(". No lens profile was found for this camera, select one with Search lens profile"),
+                        Some(lens) => hint.push_str(&format!(". Lens profile {lens} was selected automatically, click Use detected lens to confirm it or search for another one")),
+                        None => { }
+                    }
+                    for warning in &instance_data.load_warnings {
+                        hint.push_str(&format!(". {warning}"));
+                    }
+                    if instance_data.is_identity(time)? {
+                        hint = "OK. Stabilization amount and lens correction are 0, so the clip is passed through".to_owned();
+                    }
+                    instance_data.param_status.set_hint(&hint)?;
                     if !instance_data.param_status.get_value()? {
                         instance_data.param_status.set_value(true)?;
                         instance_data.update_loaded_state(true);
                     }
                 }
 
-                speed_stretch *= src_fps / fps;
+                let speed_stretch = instance_data.speed_stretch(params.frame_count, fps);
 
                 let mut time = time;
                 let mut timestamp_us = ((time / src_fps * 1_000_000.0) * speed_stretch).round() as i64;
@@ -606,10 +1902,10 @@ impl Execute for GyroflowPlugin {
 
                 let source_rect: RectI = source_image.get_region_of_definition()?;
 
-                let src_stride = source_image.get_row_bytes()? as usize;
-                let out_stride = output_image.get_row_bytes()? as usize;
-                let src_size = ((source_rect.x2 - source_rect.x1) as usize, (source_rect.y2 - source_rect.y1) as usize, src_stride);
-                let out_size = ((output_rect.x2 - output_rect.x1) as usize, (output_rect.y2 - output_rect.y1) as usize, out_stride);
+                let src_stride = source_image.get_row_bytes()? as isize;
+                let out_stride = output_image.get_row_bytes()? as isize;
+                let src_size = ((source_rect.x2 - source_rect.x1) as usize, (source_rect.y2 - source_rect.y1) as usize, src_stride.unsigned_abs());
+                let out_size = ((output_rect.x2 - output_rect.x1) as usize, (output_rect.y2 - output_rect.y1) as usize, out_stride.unsigned_abs());
 
                 let src_rect = InstanceData::get_center_rect(src_size.0, src_size.1, org_ratio);
 
@@ -641,6 +1937,31 @@ impl Execute for GyroflowPlugin {
                 // log::debug!("src_size: {src_size:?} | src_rect: {src_rect:?}");
                 // log::debug!("out_size: {out_size:?} | out_rect: {out_rect:?}");
 
+                // The host may pass only the region of interest of the source and a tile of the output, tiles are only enabled without GPU rendering.
+                // The stabilization renders whole frames, so the frame is rendered once into the full frame buffers kept by the instance,
+                // and the tiles of the same frame are copied from them as long as the source doesn't change.
+                // The stabilization expects the rows going up in memory, so images with negative row bytes are rendered through these buffers as well
+                let src_bounds = source_image.get_bounds()?;
+                let out_bounds = output_image.get_bounds()?;
+                let bpp = bytes_per_pixel(output_image.get_pixel_depth()?, output_image.get_components()?);
+                let mut tiled_render = None;
+                let mut tiled_key = String::new();
+                if !gpu && (tiles::is_tile(&src_bounds, &source_rect) || tiles::is_tile(&out_bounds, &output_rect) || src_stride < 0 || out_stride < 0) {
+                    tiled_key = format!("{:x}|{timestamp_us}|{input_rotation:?}|{out_rect:?}|{output_rect:?}|{bpp}|{}", Arc::as_ptr(&stab) as usize, interpolation as i32);
+                    let src = CpuImage { data: cpu_buffer!(source_image), stride: src_stride, bounds: src_bounds };
+                    let mut render = instance_data.tiled_render.take().unwrap_or_else(|| TiledRender::new(source_rect, output_rect, bpp));
+                    if render.key == tiled_key && tiles::same_region(&src, &render.source.image(), bpp) {
+                        tiles::copy_region(&render.output.image(), &mut CpuImage { data: cpu_buffer!(output_image), stride: out_stride, bounds: out_bounds }, bpp);
+                        instance_data.tiled_render = Some(render);
+                        return OK;
+                    }
+                    render.key.clear();
+                    render.source.reset(source_rect, bpp);
+                    render.output.reset(output_rect, bpp);
+                    tiles::copy_region(&src, &mut render.source.image(), bpp);
+                    tiled_render = Some(render);
+                }
+
                 let mut buffers =
                     if in_args.get_opencl_enabled().unwrap_or_default() {
                         use std::ffi::c_void;
@@ -719,8 +2040,8 @@ impl Execute for GyroflowPlugin {
                         let out_texture = output_image.get_opengl_texture_index()? as u32;
                         let mut src_size = src_size;
                         let mut out_size = out_size;
-                        src_size.2 = src_size.0 * 4 * match source_image.get_pixel_depth()? { BitDepth::None => 0, BitDepth::Byte => 1, BitDepth::Short => 2, BitDepth::Half => 2, BitDepth::Float => 4 };
-                        out_size.2 = out_size.0 * 4 * match output_image.get_pixel_depth()? { BitDepth::None => 0, BitDepth::Byte => 1, BitDepth::Short => 2, BitDepth::Half => 2, BitDepth::Float => 4 };
+                        src_size.2 = src_size.0 * bytes_per_pixel(source_image.get_pixel_depth()?, source_image.get_components()?);
+                        out_size.2 = out_size.0 * bytes_per_pixel(output_image.get_pixel_depth()?, output_image.get_components()?);
 
                         // log::info!("OpenGL in: {texture}, out: {out_texture} src_size: {src_size:?}, out_size: {out_size:?}, in_rect: {src_rect:?}, out_rect: {out_rect:?}");
                         Some(Buffers {
@@ -740,21 +2061,15 @@ impl Execute for GyroflowPlugin {
                             }
                         })
                     } else {
-                        use std::slice::from_raw_parts_mut;
-                        let src_buf = unsafe { match source_image.get_pixel_depth()? {
-                            BitDepth::None  => { return FAILED; }
-                            BitDepth::Byte  => { let b = source_image.get_descriptor::<RGBAColourB>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) },
-                            BitDepth::Short => { let b = source_image.get_descriptor::<RGBAColourS>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) },
-                            BitDepth::Half  => { let b = source_image.get_descriptor::<RGBAColourS>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) },
-                            BitDepth::Float => { let b = source_image.get_descriptor::<RGBAColourF>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) }
-                        } };
-                        let dst_buf = unsafe { match output_image.get_pixel_depth()? {
-                            BitDepth::None  => { return FAILED; }
-                            BitDepth::Byte  => { let b = output_image.get_descriptor::<RGBAColourB>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) },
-                            BitDepth::Short => { let b = output_image.get_descriptor::<RGBAColourS>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) },
-                            BitDepth::Half  => { let b = output_image.get_descriptor::<RGBAColourS>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) },
-                            BitDepth::Float => { let b = output_image.get_descriptor::<RGBAColourF>()?; let mut b = b.data(); from_raw_parts_mut(b.ptr_mut(0), b.bytes()) }
-                        } };
+                        let (mut src_size, mut out_size) = (src_size, out_size);
+                        let (src_buf, dst_buf) = match tiled_render.as_mut() {
+                            Some(render) => {
+                                src_size.2 = render.source.stride;
+                                out_size.2 = render.output.stride;
+                                (&mut render.source.data[..], &mut render.output.data[..])
+                            },
+                            None => (cpu_buffer!(source_image), cpu_buffer!(output_image))
+                        };
 
                         Some(Buffers {
                             input: BufferDescription {
@@ -776,26 +2091,41 @@ impl Execute for GyroflowPlugin {
 
                 if effect.abort()? { return FAILED; }
 
-                if let Some(ref mut buffers) = buffers {
-                    let processed = match output_image.get_pixel_depth()? {
-                        BitDepth::None  => { return FAILED; },
-                        BitDepth::Byte  => stab.process_pixels::<RGBA8>  (timestamp_us, None, buffers),
-                        BitDepth::Short => stab.process_pixels::<RGBA16> (timestamp_us, None, buffers),
-                        BitDepth::Half  => stab.process_pixels::<RGBAf16>(timestamp_us, None, buffers),
-                        BitDepth::Float => stab.process_pixels::<RGBAf>  (timestamp_us, None, buffers)
-                    };
-                    match processed {
-                        Ok(_) => {
-                            // log::info!("Rendered | {}x{} in {:.2}ms: {:?}", src_size.0, src_size.1, _time.elapsed().as_micros() as f64 / 1000.0, _);
-                            OK
-                        },
-                        Err(e) => {
-                            log::warn!("Failed to render: {e:?}");
-                            FAILED
+                let Some(mut buffers) = buffers else { return FAILED; };
+                let processed = {
+                    let buffers = &mut buffers;
+                    match (output_image.get_pixel_depth()?, output_image.get_components()?) {
+                        (BitDepth::None, _) => { return FAILED; },
+                        (BitDepth::Byte,  ImageComponent::RGB)   => stab.process_pixels::<RGB8>   (timestamp_us, None, buffers),
+                        (BitDepth::Short, ImageComponent::RGB)   => stab.process_pixels::<RGB16>  (timestamp_us, None, buffers),
+                        (BitDepth::Half,  ImageComponent::RGB)   => stab.process_pixels::<RGBf16> (timestamp_us, None, buffers),
+                        (BitDepth::Float, ImageComponent::RGB)   => stab.process_pixels::<RGBf>   (timestamp_us, None, buffers),
+                        (BitDepth::Byte,  ImageComponent::Alpha) => stab.process_pixels::<Luma8>  (timestamp_us, None, buffers),
+                        (BitDepth::Short, ImageComponent::Alpha) => stab.process_pixels::<Luma16> (timestamp_us, None, buffers),
+                        (BitDepth::Half,  ImageComponent::Alpha) => stab.process_pixels::<Lumaf16>(timestamp_us, None, buffers),
+                        (BitDepth::Float, ImageComponent::Alpha) => stab.process_pixels::<Lumaf>  (timestamp_us, None, buffers),
+                        (BitDepth::Byte,  _) => stab.process_pixels::<RGBA8>  (timestamp_us, None, buffers),
+                        (BitDepth::Short, _) => stab.process_pixels::<RGBA16> (timestamp_us, None, buffers),
+                        (BitDepth::Half,  _) => stab.process_pixels::<RGBAf16>(timestamp_us, None, buffers),
+                        (BitDepth::Float, _) => stab.process_pixels::<RGBAf>  (timestamp_us, None, buffers)
+                    }
+                };
+                // The buffers borrow the tiled render
+                drop(buffers);
+                match processed {
+                    Ok(_) => {
+                        if let Some(mut render) = tiled_render {
+                            tiles::copy_region(&render.output.image(), &mut CpuImage { data: cpu_buffer!(output_image), stride: out_stride, bounds: out_bounds }, bpp);
+                            render.key = tiled_key;
+                            instance_data.tiled_render = Some(render);
                         }
+                        // log::info!("Rendered | {}x{} in {:.2}ms: {:?}", src_size.0, src_size.1, _time.elapsed().as_micros() as f64 / 1000.0, _);
+                        OK
+                    },
+                    Err(e) => {
+                        log::warn!("Failed to render: {e:?}");
+                        FAILED
                     }
-                } else {
-                    FAILED
                 }
             }
 
@@ -806,6 +2136,21 @@ impl Execute for GyroflowPlugin {
                 let source_clip = effect.get_simple_input_clip()?;
                 let output_clip = effect.get_output_clip()?;
 
+                let smoothing_params = smoothing_params::all().into_iter().map(|p| {
+                    let handle = if p.is_checkbox {
+                        SmoothingParamHandle::Bool(param_set.parameter(&p.ofx_name)?)
+                    } else {
+                        SmoothingParamHandle::Double(param_set.parameter(&p.ofx_name)?)
+                    };
+                    Ok((p, handle))
+                }).collect::<Result<Vec<_>>>()?;
+                let keyframable_smoothing = smoothing_params.iter()
+                    .filter_map(|(p, handle)| match handle {
+                        SmoothingParamHandle::Double(param) => Some((p.algorithm, p.keyframe?, param.clone())),
+                        _ => None
+                    })
+                    .collect();
+
                 let mut instance_data = InstanceData {
                     source_clip,
                     output_clip,
@@ -813,16 +2158,34 @@ impl Execute for GyroflowPlugin {
                     param_project_data:             param_set.parameter("ProjectData")?,
                     param_embedded_lens:            param_set.parameter("EmbeddedLensProfile")?,
                     param_embedded_preset:          param_set.parameter("EmbeddedPreset")?,
+                    param_smoothing_algorithm_name: param_set.parameter("SmoothingAlgorithmName")?,
+                    param_autosync_offsets:         param_set.parameter("AutosyncOffsets")?,
+                    param_motion_data_file:         param_set.parameter("MotionDataFile")?,
+                    param_lens_search:              param_set.parameter("LensSearch")?,
+                    param_lens_profile_name:        param_set.parameter("LensProfileName")?,
                     param_project_path:             param_set.parameter("gyrodata")?,
                     param_disable_stretch:          param_set.parameter("DisableStretch")?,
                     param_status:                   param_set.parameter("Status")?,
                     param_open_in_gyroflow:         param_set.parameter("OpenGyroflow")?,
                     param_reload_project:           param_set.parameter("ReloadProject")?,
+                    param_save_project:             param_set.parameter("SaveProject")?,
+                    param_auto_reload:              param_set.parameter("AutoReload")?,
+                    param_auto_reload_values:       param_set.parameter("AutoReloadValues")?,
                     param_toggle_overview:          param_set.parameter("ToggleOverview")?,
                     param_dont_draw_outside:        param_set.parameter("DontDrawOutside")?,
                     param_include_project_data:     param_set.parameter("IncludeProjectData")?,
                     param_input_rotation:           param_set.parameter("InputRotation")?,
-                    gyrodata:                       LruCache::new(std::num::NonZeroUsize::new(20).unwrap()),
+                    param_per_axis:                 param_set.parameter("PerAxisSmoothness")?,
+                    param_zoom_mode:                param_set.parameter("ZoomMode")?,
+                    param_analyze_crop_mode:        param_set.parameter("AnalyzeCropMode")?,
+                    param_horizon_lock_mode:        param_set.parameter("HorizonLockMode")?,
+                    param_interpolation:            param_set.parameter("Interpolation")?,
+                    param_analyze_segment:          param_set.parameter("AnalyzeSegmentLength")?,
+                    smoothing_params,
+                    gyrodata:                       LruCache::new(MANAGER_CACHE.lock().instance_capacity()),
+                    project_job:                    None,
+                    setup_job:                      None,
+                    failed_project_key:             None,
                     original_output_size:           (0, 0),
                     original_video_size:            (0, 0),
                     num_frames:                     0,
@@ -830,33 +2193,63 @@ impl Execute for GyroflowPlugin {
                     current_file_info:              Arc::new(Mutex::new(None)),
                     current_file_info_pending:      Arc::new(AtomicBool::new(false)),
                     reload_values_from_project:     false,
-                    ever_changed:                   false,
+                    reset_sync_offset:              false,
+                    project_watcher:                None,
+                    zoom_limited:                   None,
+                    project_offsets:                Vec::new(),
+                    project_zoom_window:            0.0,
+                    autosync_job:                   None,
+                    autosync_error:                 None,
+                    load_warnings:                  Vec::new(),
+                    render_trigger:                 RenderTrigger::new(param_set.parameter("RenderRevision")?),
+                    tiled_render:                   None,
+                    lens_results:                   Vec::new(),
+                    lens_result_index:              None,
+                    auto_lens:                      None,
                     opencl_disabled:                false,
                     keyframable_params: Arc::new(RwLock::new(KeyframableParams {
                         fov:                      param_set.parameter("FOV")?,
+                        zoom_window:              param_set.parameter("ZoomWindow")?,
+                        max_zoom:                 param_set.parameter("MaxZoom")?,
+                        stabilization_amount:     param_set.parameter("StabilizationAmount")?,
+                        sync_offset:              param_set.parameter("SyncOffset")?,
                         smoothness:               param_set.parameter("Smoothness")?,
+                        smoothness_pitch:         param_set.parameter("SmoothnessPitch")?,
+                        smoothness_yaw:           param_set.parameter("SmoothnessYaw")?,
+                        smoothness_roll:          param_set.parameter("SmoothnessRoll")?,
                         lens_correction_strength: param_set.parameter("LensCorrectionStrength")?,
                         horizon_lock_amount:      param_set.parameter("HorizonLockAmount")?,
                         horizon_lock_roll:        param_set.parameter("HorizonLockRoll")?,
+                        horizon_lock_pitch:       param_set.parameter("HorizonLockPitch")?,
                         video_speed:              param_set.parameter("VideoSpeed")?,
                         positionx:                param_set.parameter("PositionX")?,
                         positiony:                param_set.parameter("PositionY")?,
                         rotation:                 param_set.parameter("Rotation")?,
                         use_gyroflows_keyframes:  param_set.parameter("UseGyroflowsKeyframes")?,
                         use_gyroflows_cached:     param_set.parameter::<Bool>("UseGyroflowsKeyframes")?.get_value()?,
-                        cached_keyframes:         KeyframeManager::default()
+                        smoothing_algorithm:      param_set.parameter("SmoothingAlgorithm")?,
+                        smoothing:                keyframable_smoothing,
+                        cached_keyframes:         KeyframeManager::default(),
+                        cached_max_zoom:          Vec::new(),
+                        cached_stab_amount:       Vec::new(),
+                        cached_sync_offset:       Vec::new(),
+                        cached_hash:              0
                     })),
                 };
                 if instance_data.param_instance_id.get_value()?.is_empty() {
-                    instance_data.ever_changed = true;
                     instance_data.param_instance_id.set_value(format!("{}", fastrand::u64(..)))?;
                 }
+                if instance_data.param_project_path.get_value()?.is_empty() {
+                    instance_data.set_no_project_status()?;
+                }
+                instance_data.restore_smoothing_algorithm()?;
 
                 effect.set_instance_data(instance_data)?;
 
                 OK
             }
             InstanceChanged(ref mut effect, ref mut in_args) => {
+                effect.get_instance_data::<InstanceData>()?.render_trigger.apply()?;
                 if in_args.get_name()? == "Browse" {
                     let instance_data: &mut InstanceData = effect.get_instance_data()?;
                     let mut d = rfd::FileDialog::new()
@@ -900,6 +2293,8 @@ impl Execute for GyroflowPlugin {
                             if let Ok(contents) = std::fs::read_to_string(&d) {
                                 if d.ends_with(".json") {
                                     instance_data.param_embedded_lens.set_value(contents)?;
+                                    let name = std::path::Path::new(&d).file_stem().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+                                    instance_data.param_lens_profile_name.set_value(name)?;
                                 } else {
                                     instance_data.param_embedded_preset.set_value(contents)?;
                                 }
@@ -908,6 +2303,60 @@ impl Execute for GyroflowPlugin {
                         }
                     }
                 }
+                if in_args.get_name()? == "BrowseMotionData" {
+                    let instance_data: &mut InstanceData = effect.get_instance_data()?;
+                    let mut d = rfd::FileDialog::new()
+                        .add_filter("Motion data files", &["bbl", "bfl", "csv", "gcsv", "txt", "log", "bin", "json"])
+                        .add_filter("All files", &["*"]);
+                    let current_path = instance_data.param_motion_data_file.get_value()?;
+                    let current_path = if current_path.is_empty() { instance_data.param_project_path.get_value()? } else { current_path };
+                    if !current_path.is_empty() {
+                        if let Some(path) = std::path::Path::new(&current_path).parent() {
+                            d = d.set_directory(path);
+                        }
+                    }
+                    if let Some(d) = d.pick_file() {
+                        instance_data.param_motion_data_file.set_value(d.display().to_string())?;
+                        instance_data.clear_stab();
+                    }
+                }
+                if in_args.get_name()? == "MotionDataFile" {
+                    effect.get_instance_data::<InstanceData>()?.clear_stab();
+                }
+                if in_args.get_name()? == "LensSearch" && in_args.get_change_reason()? == Change::UserEdited {
+                    let instance_data: &mut InstanceData = effect.get_instance_data()?;
+                    // Only the results are updated while typing, a profile is applied with Previous/Next
+                    instance_data.lens_results = lens_profiles::search(&instance_data.param_lens_search.get_value()?);
+                    instance_data.lens_result_index = None;
+                    instance_data.param_lens_search.set_hint(&match instance_data.lens_results.len() {
+                        0 => "No matching lens profiles".to_owned(),
+                        n => format!("{n} matching lens profiles, select one with Next lens or Previous lens"),
+                    })?;
+                }
+                if in_args.get_name()? == "ConfirmLens" {
+                    let instance_data: &mut InstanceData = effect.get_instance_data()?;
+                    let lens = instance_data.managers().first().filter(|x| has_lens_profile(x)).and_then(|x| x.lens.read().get_json().ok());
+                    if let (Some(json), Some(name)) = (lens, instance_data.auto_lens.clone().filter(|x| !x.is_empty())) {
+                        instance_data.param_embedded_lens.set_value(json)?;
+                        instance_data.param_lens_profile_name.set_value(name)?;
+                        instance_data.auto_lens = None;
+                    }
+                }
+                if in_args.get_name()? == "PreviousLens" || in_args.get_name()? == "NextLens" {
+                    let instance_data: &mut InstanceData = effect.get_instance_data()?;
+                    if instance_data.lens_results.is_empty() {
+                        instance_data.lens_results = lens_profiles::search(&instance_data.param_lens_search.get_value()?);
+                        instance_data.lens_result_index = None;
+                    }
+                    let count = instance_data.lens_results.len().max(1);
+                    let index = match (in_args.get_name()? == "NextLens", instance_data.lens_result_index) {
+                        (true,  Some(i)) => i + 1,
+                        (true,  None)    => 0,
+                        (false, Some(i)) => i + count - 1,
+                        (false, None)    => count - 1,
+                    };
+                    instance_data.select_lens_profile(index % count)?;
+                }
                 if in_args.get_name()? == "OpenGyroflow" {
                     effect.get_instance_data::<InstanceData>()?.open_gyroflow();
                 }
@@ -921,9 +2370,32 @@ impl Execute for GyroflowPlugin {
                 if in_args.get_name()? == "gyrodata" || in_args.get_name()? == "ReloadProject" || in_args.get_name()? == "DontDrawOutside" {
                     let instance_data = effect.get_instance_data::<InstanceData>()?;
                     if in_args.get_name()? == "gyrodata" || in_args.get_name()? == "ReloadProject" {
-                        instance_data.reload_values_from_project = true;
+                        instance_data.request_reload_values(in_args.get_change_reason()? == Change::UserEdited);
                     }
                     instance_data.clear_stab();
+                    // It's passed through, so there's no render to update the status
+                    if instance_data.param_project_path.get_value()?.is_empty() {
+                        instance_data.set_no_project_status()?;
+                    }
+                }
+                if in_args.get_name()? == "AutoReload" {
+                    let instance_data = effect.get_instance_data::<InstanceData>()?;
+                    let enabled = instance_data.param_auto_reload.get_value()?;
+                    instance_data.param_auto_reload_values.set_enabled(enabled)?;
+                    instance_data.check_project_changed()?;
+                }
+                if in_args.get_name()? == "SaveProject" {
+                    effect.get_instance_data::<InstanceData>()?.save_to_project()?;
+                }
+                if in_args.get_name()? == "Autosync" {
+                    effect.get_instance_data::<InstanceData>()?.start_autosync()?;
+                }
+                if in_args.get_name()? == "AnalyzeCrop" {
+                    effect.get_instance_data::<InstanceData>()?.analyze_crop()?;
+                }
+                if in_args.get_name()? == "AnalyzeCropMode" {
+                    let instance_data = effect.get_instance_data::<InstanceData>()?;
+                    instance_data.param_analyze_segment.set_enabled(instance_data.param_analyze_crop_mode.get_value()? == CROP_PER_SEGMENT)?;
                 }
                 if in_args.get_name()? == "IncludeProjectData" {
                     let instance_data = effect.get_instance_data::<InstanceData>()?;
@@ -934,7 +2406,7 @@ impl Execute for GyroflowPlugin {
                                 if StabilizationManager::project_has_motion_data(data.as_bytes()) {
                                     instance_data.param_project_data.set_value(data.clone())?;
                                 } else {
-                                    if let Some((_, stab)) = instance_data.gyrodata.peek_lru() {
+                                    if let Some(stab) = instance_data.gyrodata.peek_lru().and_then(|(_, v)| v.upgrade()) {
                                         if let Ok(data) = stab.export_gyroflow_data(gyroflow_core::GyroflowProjectType::WithGyroData, "{}", None) {
                                             instance_data.param_project_data.set_value(data)?;
                                         }
@@ -944,7 +2416,7 @@ impl Execute for GyroflowPlugin {
                                 instance_data.param_project_data.set_value("".to_string())?;
                             }
                         } else {
-                            if let Some((_, stab)) = instance_data.gyrodata.peek_lru() {
+                            if let Some(stab) = instance_data.gyrodata.peek_lru().and_then(|(_, v)| v.upgrade()) {
                                 if let Ok(data) = stab.export_gyroflow_data(gyroflow_core::GyroflowProjectType::WithGyroData, "{}", None) {
                                     instance_data.param_project_data.set_value(data)?;
                                 }
@@ -960,25 +2432,36 @@ impl Execute for GyroflowPlugin {
                 }
                 if in_args.get_change_reason()? == Change::UserEdited {
                     match in_args.get_name()?.as_ref() {
-                        "FOV" | "Smoothness" | "LensCorrectionStrength" |
-                        "HorizonLockAmount" | "HorizonLockRoll" |
+                        "FOV" | "ZoomMode" | "ZoomWindow" | "MaxZoom" | "Smoothness" | "SmoothnessPitch" | "SmoothnessYaw" | "SmoothnessRoll" | "PerAxisSmoothness" | "LensCorrectionStrength" |
+                        "HorizonLockAmount" | "HorizonLockRoll" | "HorizonLockMode" | "HorizonLockPitch" | "StabilizationAmount" | "SyncOffset" |
                         "PositionX" | "PositionY" | "Rotation" | "InputRotation" | "VideoSpeed" |
                         "UseGyroflowsKeyframes" | "RecalculateKeyframes" => {
                             let instance_data: &mut InstanceData = effect.get_instance_data()?;
-                            instance_data.param_status.set_label("Calculating...")?;
-                            if !instance_data.ever_changed {
-                                instance_data.ever_changed = true;
-                                instance_data.param_instance_id.set_value(format!("{}", fastrand::u64(..)))?;
-                                instance_data.clear_stab();
+                            if in_args.get_name()? == "PerAxisSmoothness" || in_args.get_name()? == "ZoomMode" || in_args.get_name()? == "HorizonLockMode" {
+                                instance_data.update_loaded_state(!instance_data.managers().is_empty());
                             }
-                            instance_data.keyframable_params.write().cache_keyframes(instance_data.num_frames, instance_data.fps.max(1.0));
-                            for (_, v) in instance_data.gyrodata.iter_mut() {
-                                match in_args.get_name()?.as_ref() {
-                                    "Smoothness" | "HorizonLockAmount" | "HorizonLockRoll" | "RecalculateKeyframes" => { v.recompute_smoothness(); v.recompute_adaptive_zoom(); },
-                                    "LensCorrectionStrength" | "PositionX" | "PositionY" | "Rotation" => { v.recompute_adaptive_zoom(); },
-                                    _ => { }
-                                }
-                                v.recompute_undistortion();
+                            instance_data.param_status.set_label("Calculating...")?;
+                            instance_data.keyframable_params.write().cache_keyframes(instance_data.fps.max(1.0));
+                            instance_data.detach_shared();
+                            let (amount, max_zoom) = {
+                                let kparams = instance_data.keyframable_params.read();
+                                (kparams.cached_stab_amount.clone(), kparams.cached_max_zoom.clone())
+                            };
+                            for v in instance_data.managers() {
+                                let smoothing = match in_args.get_name()?.as_ref() {
+                                    "Smoothness" | "SmoothnessPitch" | "SmoothnessYaw" | "SmoothnessRoll" | "StabilizationAmount" |
+                                    "HorizonLockAmount" | "HorizonLockRoll" | "HorizonLockPitch" | "RecalculateKeyframes" |
+                                    "VideoSpeed" | "UseGyroflowsKeyframes" => true,
+                                    "HorizonLockMode" => { instance_data.apply_horizon_lock(&v)?; true },
+                                    "PerAxisSmoothness" => { instance_data.apply_smoothing(&v, Some("PerAxisSmoothness"))?; true },
+                                    "ZoomMode" | "ZoomWindow" => { instance_data.apply_zoom(&v)?; false },
+                                    "SyncOffset" => {
+                                        // The smoothing is computed from the synced gyro data
+                                        InstanceData::apply_sync_offset(&v, &instance_data.project_offsets, &instance_data.keyframable_params.read().cached_sync_offset);
+                                        true
+                                    },
+                                    _ => false
+                                };
                                 match in_args.get_name()?.as_ref() {
                                     "VideoSpeed" | "UseGyroflowsKeyframes" | "RecalculateKeyframes" => {
                                         let inverse = !(instance_data.keyframable_params.read().use_gyroflows_keyframes.get_value()? && v.keyframes.read().is_keyframed_internally(&KeyframeType::VideoSpeed));
@@ -986,20 +2469,57 @@ impl Execute for GyroflowPlugin {
                                     },
                                     _ => { }
                                 }
+                                if smoothing {
+                                    InstanceData::recompute_smoothness(&v, &amount, &max_zoom);
+                                } else {
+                                    InstanceData::recompute_zoom(&v, &max_zoom);
+                                }
                             }
+                            instance_data.rekey_managers();
                         },
                         _ => { }
                     }
                 }
 
+                if in_args.get_change_reason()? == Change::UserEdited {
+                    let name = in_args.get_name()?;
+                    let instance_data: &mut InstanceData = effect.get_instance_data()?;
+                    if name == "SmoothingAlgorithm" || instance_data.smoothing_params.iter().any(|(p, _)| p.ofx_name == name) {
+                        if name == "SmoothingAlgorithm" {
+                            instance_data.store_smoothing_algorithm_name(instance_data.keyframable_params.read().smoothing_algorithm.get_value()? as usize)?;
+                        }
+                        instance_data.update_loaded_state(!instance_data.managers().is_empty());
+                        instance_data.param_status.set_label("Calculating...")?;
+                        instance_data.keyframable_params.write().cache_keyframes(instance_data.fps.max(1.0));
+                        instance_data.detach_shared();
+                        let (amount, max_zoom) = {
+                            let kparams = instance_data.keyframable_params.read();
+                            (kparams.cached_stab_amount.clone(), kparams.cached_max_zoom.clone())
+                        };
+                        // A new algorithm gets all the values which were changed, otherwise only the edited one
+                        let edited = if name == "SmoothingAlgorithm" { None } else { Some(name.as_str()) };
+                        for v in instance_data.managers() {
+                            instance_data.apply_smoothing(&v, edited)?;
+                            InstanceData::recompute_smoothness(&v, &amount, &max_zoom);
+                        }
+                        instance_data.rekey_managers();
+                    }
+                }
+
                 if in_args.get_name()? == "ToggleOverview" && in_args.get_change_reason()? == Change::UserEdited {
                     let instance_data: &mut InstanceData = effect.get_instance_data()?;
 
                     let on = instance_data.param_toggle_overview.get_value()?;
-                    for (_, v) in instance_data.gyrodata.iter_mut() {
+                    instance_data.detach_shared();
+                    let (amount, max_zoom) = {
+                        let kparams = instance_data.keyframable_params.read();
+                        (kparams.cached_stab_amount.clone(), kparams.cached_max_zoom.clone())
+                    };
+                    for v in instance_data.managers() {
                         v.set_fov_overview(on);
-                        v.recompute_undistortion();
+                        InstanceData::recompute_smoothness(&v, &amount, &max_zoom);
                     }
+                    instance_data.rekey_managers();
                 }
 
                 OK
@@ -1008,19 +2528,39 @@ impl Execute for GyroflowPlugin {
             GetRegionOfDefinition(ref mut effect, ref in_args, ref mut out_args) => {
                 let time = in_args.get_time()?;
                 let instance_data = effect.get_instance_data::<InstanceData>()?;
-                let rod = instance_data.source_clip.get_region_of_definition(time)?;
-                let mut out_rod = rod;
-                if instance_data.original_output_size != (0, 0) && !instance_data.param_dont_draw_outside.get_value_at_time(time)? {
-                    out_rod.x2 = instance_data.original_output_size.0 as f64;
-                    out_rod.y2 = instance_data.original_output_size.1 as f64;
-                }
+                let out_rod = instance_data.output_region_of_definition(time)?;
                 out_args.set_effect_region_of_definition(out_rod)?;
 
                 OK
             }
 
+            IsIdentity(ref mut effect, ref in_args, ref mut out_args) => {
+                let time = in_args.get_time()?;
+                let instance_data: &mut InstanceData = effect.get_instance_data()?;
+                // Nothing to stabilize, the host shows the source instead of failed frames. The status is updated by the render and param changes
+                let path = instance_data.param_project_path.get_value()?;
+                let failed = instance_data.failed_project_key.is_some() && instance_data.failed_project_key == Some(instance_data.project_key(&path)?);
+                if !path.is_empty() && !failed && !instance_data.is_identity(time)? {
+                    return REPLY_DEFAULT;
+                }
+                out_args.set_name(&image_effect_simple_source_clip_name())?;
+
+                OK
+            }
+
+            GetRegionsOfInterest(ref mut effect, ref in_args, ref mut out_args) => {
+                let time = in_args.get_time()?;
+                let instance_data = effect.get_instance_data::<InstanceData>()?;
+                let source_roi = instance_data.source_region_of_interest(time)?;
+                out_args.set_raw(image_clip_prop_roi!(clip_source!()), &source_roi)?;
+
+                OK
+            }
+
             DestroyInstance(ref mut effect) => {
-                effect.get_instance_data::<InstanceData>()?.clear_stab();
+                let instance_data = effect.get_instance_data::<InstanceData>()?;
+                instance_data.render_trigger.cancel();
+                instance_data.release_stab();
                 OK
             },
             PurgeCaches(ref mut effect) => {
@@ -1030,10 +2570,10 @@ impl Execute for GyroflowPlugin {
 
             DescribeInContext(ref mut effect, ref _in_args) => {
                 let mut output_clip = effect.new_output_clip()?;
-                output_clip.set_supported_components(&[ImageComponent::RGBA])?;
+                output_clip.set_supported_components(&[ImageComponent::RGBA, ImageComponent::RGB, ImageComponent::Alpha])?;
 
                 let mut input_clip = effect.new_simple_input_clip()?;
-                input_clip.set_supported_components(&[ImageComponent::RGBA])?;
+                input_clip.set_supported_components(&[ImageComponent::RGBA, ImageComponent::RGB, ImageComponent::Alpha])?;
 
                 let mut param_set = effect.parameter_set()?;
 
@@ -1044,7 +2584,11 @@ impl Execute for GyroflowPlugin {
                     param_set.param_define_string("InstanceId")?
                              .set_secret(true)?;
 
-                    for x in ["ProjectData", "EmbeddedLensProfile", "EmbeddedPreset"] {
+                    // Changed when a background job is done, so the host renders the frames again
+                    param_set.param_define_double("RenderRevision")?
+                             .set_secret(true)?;
+
+                    for x in ["ProjectData", "EmbeddedLensProfile", "EmbeddedPreset", "AutosyncOffsets", "SmoothingAlgorithmName"] {
                         let mut param = param_set.param_define_string(x)?;
                         let _ = param.set_script_name(x);
                         param.set_secret(true)?;
@@ -1069,21 +2613,84 @@ impl Execute for GyroflowPlugin {
                     param.set_hint("Browse for the Gyroflow project file")?;
                     param.set_parent("ProjectGroup")?;
 
+                    let mut param = param_set.param_define_string("MotionDataFile")?;
+                    param.set_string_type(ParamStringType::SingleLine)?;
+                    param.set_label("Motion data file")?;
+                    param.set_hint("Gyro log from an external logger (eg. Blackbox or a .gcsv file), used when the project file is a video")?;
+                    let _ = param.set_script_name("MotionDataFile");
+                    param.set_parent("ProjectGroup")?;
+
+                    let mut param = param_set.param_define_button("BrowseMotionData")?;
+                    param.set_label("Browse motion data")?;
+                    param.set_hint("Browse for the motion data file")?;
+                    param.set_parent("ProjectGroup")?;
+
                     let mut param = param_set.param_define_button("LoadLens")?;
                     param.set_label("Load preset/lens profile")?;
                     param.set_hint("Browse for the lens profile or a preset")?;
                     param.set_parent("ProjectGroup")?;
 
+                    let mut param = param_set.param_define_string("LensSearch")?;
+                    param.set_string_type(ParamStringType::SingleLine)?;
+                    param.set_label("Search lens profile")?;
+                    param.set_hint("Search the lens profiles included in the plugin by camera brand, model, lens or resolution, eg. \"gopro 11 wide 4k\"")?;
+                    let _ = param.set_script_name("LensSearch");
+                    param.set_parent("ProjectGroup")?;
+
+                    let mut param = param_set.param_define_string("LensProfileName")?;
+                    param.set_string_type(ParamStringType::SingleLine)?;
+                    param.set_label("Lens profile")?;
+                    param.set_hint("Selected lens profile, it's used when the project file is a video")?;
+                    param.set_enabled(false)?;
+                    param.set_parent("ProjectGroup")?;
+
+                    let mut param = param_set.param_define_button("ConfirmLens")?;
+                    param.set_label("Use detected lens")?;
+                    param.set_hint("Embed the lens profile selected automatically from the video metadata, so it's kept even if the matching changes")?;
+                    param.set_parent("ProjectGroup")?;
+
+                    let mut param = param_set.param_define_button("PreviousLens")?;
+                    param.set_label("Previous lens")?;
+                    param.set_hint("Select the previous lens profile matching the search")?;
+                    param.set_parent("ProjectGroup")?;
+
+                    let mut param = param_set.param_define_button("NextLens")?;
+                    param.set_label("Next lens")?;
+                    param.set_hint("Select the next lens profile matching the search")?;
+                    param.set_parent("ProjectGroup")?;
+
                     let mut param = param_set.param_define_button("OpenGyroflow")?;
                     param.set_label("Open Gyroflow")?;
                     param.set_hint("Open project in Gyroflow")?;
                     param.set_parent("ProjectGroup")?;
 
+                    let mut param = param_set.param_define_button("Autosync")?;
+                    param.set_label("Autosync")?;
+                    param.set_hint("Synchronize the gyro data with the video using optical flow at a few points of the clip, without opening Gyroflow")?;
+                    param.set_parent("ProjectGroup")?;
+
                     let mut param = param_set.param_define_button("ReloadProject")?;
                     param.set_label("Reload project")?;
                     param.set_hint("Reload currently loaded project")?;
                     param.set_parent("ProjectGroup")?;
 
+                    let mut param = param_set.param_define_button("SaveProject")?;
+                    param.set_label("Save to project")?;
+                    param.set_hint("Write the values and keyframes from the plugin to the .gyroflow project file, so it can be opened in Gyroflow with the same settings")?;
+                    param.set_parent("ProjectGroup")?;
+
+                    let mut param = param_set.param_define_boolean("AutoReload")?;
+                    param.set_label("Reload when project file changes")?;
+                    param.set_hint("Watch the .gyroflow file and reload it automatically after it's saved again in the Gyroflow app")?;
+                    let _ = param.set_script_name("AutoReload");
+                    param.set_parent("ProjectGroup")?;
+
+                    let mut param = param_set.param_define_boolean("AutoReloadValues")?;
+                    param.set_label("Update values on reload")?;
+                    param.set_hint("When the project file changes, also apply the values and keyframes from the project. This overwrites the values adjusted in the plugin.")?;
+                    let _ = param.set_script_name("AutoReloadValues");
+                    param.set_parent("ProjectGroup")?;
+
                     let mut param = param_set.param_define_button("OpenRecentProject")?;
                     param.set_label("Last saved project")?;
                     param.set_hint("Load most recently saved project in the Gyroflow app")?;
@@ -1110,6 +2717,41 @@ impl Execute for GyroflowPlugin {
                     let _ = param.set_script_name("FOV");
                     param.set_parent("AdjustGroup")?;
 
+                    let mut param = param_set.param_define_choice("ZoomMode")?;
+                    param.set_choice_options(&["Disabled", "Dynamic zoom", "Static crop", "From project"])?;
+                    param.set_default(ZOOM_PROJECT)?;
+                    param.set_label("Zoom mode")?;
+                    param.set_hint("Adaptive zoom: dynamic zoom follows the stabilized motion, static crop uses a single zoom for the whole clip. From project uses the zooming of the loaded project")?;
+                    let _ = param.set_script_name("ZoomMode");
+                    param.set_parent("AdjustGroup")?;
+
+                    let mut param = param_set.param_define_double("ZoomWindow")?;
+                    param.set_default(4.0)?;
+                    param.set_display_min(0.1)?;
+                    param.set_display_max(15.0)?;
+                    param.set_label("Zoom window")?;
+                    param.set_hint("Smoothing window of the dynamic zoom in seconds")?;
+                    let _ = param.set_script_name("ZoomWindow");
+                    param.set_parent("AdjustGroup")?;
+
+                    let mut param = param_set.param_define_double("MaxZoom")?;
+                    param.set_default(0.0)?;
+                    param.set_display_min(0.0)?;
+                    param.set_display_max(300.0)?;
+                    param.set_label("Max zoom")?;
+                    param.set_hint("Maximum zoom of the adaptive zoom in %, parts of the frame outside of it will be visible. 0 = no limit")?;
+                    let _ = param.set_script_name("MaxZoom");
+                    param.set_parent("AdjustGroup")?;
+
+                    let mut param = param_set.param_define_double("StabilizationAmount")?;
+                    param.set_default(100.0)?;
+                    param.set_display_min(0.0)?;
+                    param.set_display_max(100.0)?;
+                    param.set_label("Stabilization amount")?;
+                    param.set_hint("Blend between the original camera motion (0%) and the stabilized one (100%), animate it to fade the stabilization in and out")?;
+                    let _ = param.set_script_name("StabilizationAmount");
+                    param.set_parent("AdjustGroup")?;
+
                     let mut param = param_set.param_define_double("Smoothness")?;
                     param.set_default(0.5)?;
                     param.set_display_min(0.01)?;
@@ -1119,6 +2761,23 @@ impl Execute for GyroflowPlugin {
                     let _ = param.set_script_name("Smoothness");
                     param.set_parent("AdjustGroup")?;
 
+                    let mut param = param_set.param_define_boolean("PerAxisSmoothness")?;
+                    param.set_label("Per-axis smoothness")?;
+                    param.set_hint("Use separate smoothness for pitch, yaw and roll, eg. to keep pans natural while removing all the roll")?;
+                    let _ = param.set_script_name("PerAxisSmoothness");
+                    param.set_parent("AdjustGroup")?;
+
+                    for (name, label) in [("SmoothnessPitch", "Pitch smoothness"), ("SmoothnessYaw", "Yaw smoothness"), ("SmoothnessRoll", "Roll smoothness")] {
+                        let mut param = param_set.param_define_double(name)?;
+                        param.set_default(0.5)?;
+                        param.set_display_min(0.01)?;
+                        param.set_display_max(3.0)?;
+                        param.set_label(label)?;
+                        param.set_hint(label)?;
+                        let _ = param.set_script_name(name);
+                        param.set_parent("AdjustGroup")?;
+                    }
+
                     let mut param = param_set.param_define_double("LensCorrectionStrength")?;
                     param.set_default(100.0)?;
                     param.set_display_min(0.0)?;
@@ -1146,6 +2805,23 @@ impl Execute for GyroflowPlugin {
                     let _ = param.set_script_name("HorizonLockRoll");
                     param.set_parent("AdjustGroup")?;
 
+                    let mut param = param_set.param_define_choice("HorizonLockMode")?;
+                    param.set_choice_options(&["Roll", "Roll and pitch"])?;
+                    param.set_default(HORIZON_LOCK_ROLL)?;
+                    param.set_label("Horizon lock mode")?;
+                    param.set_hint("Lock only the roll, or also the pitch to keep the camera looking at a fixed angle")?;
+                    let _ = param.set_script_name("HorizonLockMode");
+                    param.set_parent("AdjustGroup")?;
+
+                    let mut param = param_set.param_define_double("HorizonLockPitch")?;
+                    param.set_default(0.0)?;
+                    param.set_display_min(-90.0)?;
+                    param.set_display_max(90.0)?;
+                    param.set_label("Horizon pitch")?;
+                    param.set_hint("Pitch angle to lock to, in degrees")?;
+                    let _ = param.set_script_name("HorizonLockPitch");
+                    param.set_parent("AdjustGroup")?;
+
                     let mut param = param_set.param_define_double("PositionX")?;
                     param.set_default(0.0)?;
                     param.set_display_min(-100.0)?;
@@ -1187,12 +2863,54 @@ impl Execute for GyroflowPlugin {
                     let _ = param.set_script_name("VideoSpeed");
                     param.set_parent("AdjustGroup")?;
 
+                    let mut param = param_set.param_define_double("SyncOffset")?;
+                    param.set_default(0.0)?;
+                    param.set_display_min(-1000.0)?;
+                    param.set_display_max(1000.0)?;
+                    param.set_label("Sync offset (ms)")?;
+                    param.set_hint("Adjusts the gyro sync offsets of the project, keyframe it to correct a drift. Saving to the project includes it in the project's offsets")?;
+                    let _ = param.set_script_name("SyncOffset");
+                    param.set_parent("AdjustGroup")?;
+
                     let mut param = param_set.param_define_boolean("DisableStretch")?;
                     param.set_label("Disable Gyroflow's stretch")?;
                     param.set_hint("If you used Input stretch in the lens profile in Gyroflow, and you de-stretched the video separately in Resolve, check this to disable Gyroflow's internal stretching.")?;
                     let _ = param.set_script_name("DisableStretch");
                     param.set_parent("AdjustGroup")?;
                 }
+                {
+                    param_set.param_define_group("SmoothingGroup")?
+                             .set_label("Smoothing")?;
+
+                    let names = smoothing_params::algorithm_names();
+                    let mut param = param_set.param_define_choice("SmoothingAlgorithm")?;
+                    param.set_choice_options(&names.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+                    param.set_default(smoothing_params::default_algorithm() as i32)?;
+                    param.set_label("Smoothing algorithm")?;
+                    param.set_hint("Smoothing algorithm, the parameters of the selected one are enabled below")?;
+                    let _ = param.set_script_name("SmoothingAlgorithm");
+                    param.set_parent("SmoothingGroup")?;
+
+                    for p in smoothing_params::all() {
+                        if p.is_checkbox {
+                            let mut param = param_set.param_define_boolean(&p.ofx_name)?;
+                            param.set_default(p.default > 0.5)?;
+                            param.set_label(&p.label)?;
+                            param.set_hint(&p.label)?;
+                            let _ = param.set_script_name(&p.ofx_name);
+                            param.set_parent("SmoothingGroup")?;
+                        } else {
+                            let mut param = param_set.param_define_double(&p.ofx_name)?;
+                            param.set_default(p.default)?;
+                            param.set_display_min(p.min)?;
+                            param.set_display_max(p.max)?;
+                            param.set_label(&p.label)?;
+                            param.set_hint(&p.label)?;
+                            let _ = param.set_script_name(&p.ofx_name);
+                            param.set_parent("SmoothingGroup")?;
+                        }
+                    }
+                }
                 {
                     param_set.param_define_group("KeyframesGroup")?
                              .set_label("Keyframes")?;
@@ -1207,8 +2925,38 @@ impl Execute for GyroflowPlugin {
                     param.set_label("Recalculate keyframes")?;
                     param.set_hint("Recalculate keyframes after adjusting the splines (in Fusion mode)")?;
                     param.set_parent("KeyframesGroup")?;
+
+                    let mut param = param_set.param_define_button("AnalyzeCrop")?;
+                    param.set_label("Analyze crop")?;
+                    param.set_hint("Find the smallest zoom without any areas outside of the frame at the current smoothness, and set it as FOV. This disables the adaptive zoom")?;
+                    param.set_parent("KeyframesGroup")?;
+
+                    let mut param = param_set.param_define_choice("AnalyzeCropMode")?;
+                    param.set_choice_options(&["Whole clip", "Per segment"])?;
+                    param.set_default(CROP_WHOLE_CLIP)?;
+                    param.set_label("Analyze crop for")?;
+                    param.set_hint("Use a single FOV for the whole clip, or set FOV keyframes with the zoom needed by each segment")?;
+                    let _ = param.set_script_name("AnalyzeCropMode");
+                    param.set_parent("KeyframesGroup")?;
+
+                    let mut param = param_set.param_define_double("AnalyzeSegmentLength")?;
+                    param.set_default(2.0)?;
+                    param.set_display_min(0.1)?;
+                    param.set_display_max(10.0)?;
+                    param.set_label("Segment length")?;
+                    param.set_hint("Length of the segments in seconds")?;
+                    let _ = param.set_script_name("AnalyzeSegmentLength");
+                    param.set_enabled(false)?;
+                    param.set_parent("KeyframesGroup")?;
                 }
 
+                let mut param = param_set.param_define_choice("Interpolation")?;
+                param.set_choice_options(&["Automatic", "Bilinear", "Bicubic", "Lanczos4"])?;
+                param.set_default(INTERPOLATION_AUTO)?;
+                param.set_label("Interpolation")?;
+                let _ = param.set_script_name("Interpolation");
+                param.set_hint("Pixel interpolation quality. Automatic uses fast Bilinear while scrubbing and for draft renders, and Lanczos4 for the final render")?;
+
                 let mut param = param_set.param_define_boolean("ToggleOverview")?;
                 param.set_label("Stabilization overview")?;
                 let _ = param.set_script_name("ToggleOverview");
@@ -1228,28 +2976,28 @@ impl Execute for GyroflowPlugin {
                     .set_children(&[
                         "ProjectGroup",
                         "AdjustGroup",
+                        "SmoothingGroup",
                         "KeyframesGroup",
-                        "ToggleOverview", "DontDrawOutside", "IncludeProjectData"
+                        "Interpolation", "ToggleOverview", "DontDrawOutside", "IncludeProjectData"
                     ])?;
 
                 OK
             }
 
-            /*GetClipPreferences(ref mut effect, ref mut out_args) => {
-				let instance_data: &mut InstanceData = effect.get_instance_data()?;
-				let bit_depth = instance_data.source_clip.get_pixel_depth()?;
-				let image_component = instance_data.source_clip.get_components()?;
-				let output_component = match image_component {
-					ImageComponent::RGBA | ImageComponent::RGB => ImageComponent::RGBA,
-					_ => ImageComponent::Alpha,
-				};
-				out_args.set_raw(image_clip_prop_components!(clip_output!()), output_component.to_bytes())?;
-
-				if self.host_supports_multiple_clip_depths {
-					out_args.set_raw(image_clip_prop_depth!(clip_output!()), bit_depth.to_bytes())?;
-				}
-				OK
-			}*/
+            GetClipPreferences(ref mut effect, ref mut out_args) => {
+                // The output has the same layout as the source, so the pixels are processed without conversions.
+                // The buffers are described by their row bytes and processed with the pixel type of the components, on the CPU and the GPU
+                let instance_data: &mut InstanceData = effect.get_instance_data()?;
+                let bit_depth = instance_data.source_clip.get_pixel_depth()?;
+                let image_component = instance_data.source_clip.get_components()?;
+                out_args.set_raw(image_clip_prop_components!(clip_source!()), image_component.to_bytes())?;
+                out_args.set_raw(image_clip_prop_components!(clip_output!()), image_component.to_bytes())?;
+
+                if self.host_supports_multiple_clip_depths {
+                    out_args.set_raw(image_clip_prop_depth!(clip_output!()), bit_depth.to_bytes())?;
+                }
+                OK
+            }
 
             OpenGLContextAttached(ref mut _effect) => {
                 log::info!("OpenGLContextAttached");
@@ -1286,27 +3034,30 @@ impl Execute for GyroflowPlugin {
                 effect_properties.set_short_label("Gyroflow-old")?;
                 effect_properties.set_long_label("Gyroflow-old")?;
 
-                effect_properties.set_supported_pixel_depths(&[BitDepth::Byte, BitDepth::Short, BitDepth::Float])?;
+                effect_properties.set_supported_pixel_depths(&[BitDepth::Byte, BitDepth::Short, BitDepth::Half, BitDepth::Float])?;
                 effect_properties.set_supported_contexts(&[ImageEffectContext::Filter])?;
-                effect_properties.set_supports_tiles(false)?;
 
                 effect_properties.set_single_instance(false)?;
                 effect_properties.set_host_frame_threading(false)?;
-                effect_properties.set_render_thread_safety(ImageEffectRender::FullySafe)?;
                 effect_properties.set_supports_multi_resolution(true)?;
                 effect_properties.set_temporal_clip_access(true)?;
 
+                // Tiles are rendered on the CPU only, GPU buffers can't be assembled into whole frames
                 if supports_opengl && !supports_opencl && !supports_cuda && !supports_metal {
                     // We'll initialize the devices in OpenGLContextAttached
                     let _ = effect_properties.set_opengl_render_supported("true");
+                    effect_properties.set_supports_tiles(false)?;
+                    effect_properties.set_render_thread_safety(ImageEffectRender::FullySafe)?;
                     return OK;
                 }
 
                 let opencl_devices = gyroflow_core::gpu::opencl::OclWrapper::list_devices();
                 let wgpu_devices = gyroflow_core::gpu::wgpu::WgpuWrapper::list_devices();
+                let mut gpu = false;
                 if !opencl_devices.is_empty() {
                     let _ = effect_properties.set_opencl_render_supported("true");
                     let _ = effect_properties.set_opengl_render_supported("true");
+                    gpu = true;
                 }
 
                 let _has_metal  = wgpu_devices.iter().any(|x| x.contains("(Metal)"));
@@ -1314,9 +3065,13 @@ impl Execute for GyroflowPlugin {
                 let _has_dx12   = wgpu_devices.iter().any(|x| x.contains("(Dx12)"));
 
                 #[cfg(any(target_os = "macos", target_os = "ios"))]
-                if _has_metal { let _ = effect_properties.set_metal_render_supported("true"); }
+                if _has_metal { let _ = effect_properties.set_metal_render_supported("true"); gpu = true; }
                 #[cfg(any(target_os = "windows", target_os = "linux"))]
-                if _has_vulkan || _has_dx12 { let _ = effect_properties.set_cuda_render_supported("true"); }
+                if _has_vulkan || _has_dx12 { let _ = effect_properties.set_cuda_render_supported("true"); gpu = true; }
+
+                effect_properties.set_supports_tiles(!gpu)?;
+                // Tiles of a frame are rendered into the buffers of the instance, so they can't be rendered in parallel
+                effect_properties.set_render_thread_safety(if gpu { ImageEffectRender::FullySafe } else { ImageEffectRender::InstanceSafe })?;
 
 				if !self.log_initialized {
                     // win_dbg_logger::init();
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use gyroflow_core::lens_profile_database::LensProfileDatabase;
+use parking_lot::RwLock;
+
+const MAX_RESULTS: usize = 50;
+
+lazy_static::lazy_static! {
+    static ref DATABASE: Arc<RwLock<LensProfileDatabase>> = Arc::new(RwLock::new(LensProfileDatabase::default()));
+}
+
+// The database without loading it, for the managers. Whoever needs the profiles first loads them for everyone
+pub fn shared_database() -> Arc<RwLock<LensProfileDatabase>> {
+    DATABASE.clone()
+}
+
+// Profiles bundled with the plugin, so they're available even when the Gyroflow app isn't installed.
+// It's loaded when it's first needed
+pub fn database() -> Arc<RwLock<LensProfileDatabase>> {
+    if !DATABASE.read().loaded {
+        let mut db = DATABASE.write();
+        if !db.loaded {
+            db.load_all();
+        }
+    }
+    DATABASE.clone()
+}
+
+// Finds profiles which contain all the words of the query in their name (camera brand, model, lens and resolution).
+// Returns (name, id) of the matching profiles, official ones first
+pub fn search(query: &str) -> Vec<(String, String)> {
+    let words = query.to_lowercase().split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+    if words.is_empty() { return Vec::new(); }
+    let db = database();
+    let db = db.read();
+    let mut results = db.get_all_info().into_iter()
+        .filter(|x| {
+            let name = x.0.to_lowercase();
+            words.iter().all(|w| name.contains(w.as_str()))
+        })
+        .map(|x| (x.3, x.0, x.1))
+        .collect::<Vec<_>>();
+    results.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    results.into_iter().take(MAX_RESULTS).map(|x| (x.1, x.2)).collect()
+}
+
+pub fn profile_json(id: &str) -> Option<String> {
+    let db = database();
+    let db = db.read();
+    db.get_by_id(id)?.get_json().ok()
+}
+
+// Best matching profile for the camera metadata of a video file. The camera model has to match,
+// then profiles with the same lens and resolution are preferred
+pub fn best_match(brand: &str, model: &str, lens_model: &str, size: (usize, usize)) -> Option<(String, String)> {
+    let db = database();
+    let db = db.read();
+    best_of(db.get_all_info().into_iter().map(|x| (x.0, x.1, x.3 as usize)), brand, model, lens_model, size)
+}
+
+// Picks from (name, id, official) of the profiles
+fn best_of(profiles: impl Iterator<Item = (String, String, usize)>, brand: &str, model: &str, lens_model: &str, size: (usize, usize)) -> Option<(String, String)> {
+    let words = |x: &str| x.to_lowercase().split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+    let (brand, model, lens_model) = (words(brand), words(model), words(lens_model));
+    if model.is_empty() { return None; }
+    let resolution = format!("{}x{}", size.0, size.1);
+
+    profiles
+        .filter_map(|x| {
+            let name = x.0.to_lowercase();
+            let contains_all = |words: &[String]| words.iter().all(|w| name.contains(w.as_str()));
+            if !contains_all(&brand) || !contains_all(&model) { return None; }
+            let score = lens_model.iter().filter(|w| name.contains(w.as_str())).count() * 2
+                      + name.contains(&resolution) as usize * 3
+                      + x.2;
+            Some((score, x.0, x.1))
+        })
+        .max_by_key(|x| x.0)
+        .map(|x| (x.1, x.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles() -> impl Iterator<Item = (String, String, usize)> {
+        [
+            ("GoPro HERO11 Black Wide 4K 3840x2160",     "hero11_wide_4k",    1),
+            ("GoPro HERO11 Black Linear 2.7K 2704x1520", "hero11_linear_27k", 1),
+            ("GoPro HERO10 Black Wide 4K 3840x2160",     "hero10_wide_4k",    1),
+            ("Sony a7s III 24mm 3840x2160",              "a7s3_24mm",         0),
+        ].into_iter().map(|(name, id, official)| (name.to_owned(), id.to_owned(), official))
+    }
+
+    #[test]
+    fn best_match() {
+        let id = |brand, model, lens_model, size| best_of(profiles(), brand, model, lens_model, size).map(|x| x.1);
+        assert_eq!(id("GoPro", "HERO11 Black", "Wide", (3840, 2160)).as_deref(), Some("hero11_wide_4k"));
+        // The resolution picks the profile when the lens isn't known
+        assert_eq!(id("GoPro", "HERO11 Black", "", (2704, 1520)).as_deref(), Some("hero11_linear_27k"));
+        assert_eq!(id("Sony", "a7s III", "", (3840, 2160)).as_deref(), Some("a7s3_24mm"));
+
+        // The model has to match
+        assert_eq!(id("GoPro", "HERO99", "Wide", (3840, 2160)), None);
+        assert_eq!(id("GoPro", "", "Wide", (3840, 2160)), None);
+    }
+}
@@ -4,5 +4,12 @@ use ofx::*;
 
 mod gyroflow;
 mod fuscript;
+mod manager_cache;
+mod project_watcher;
+mod background_job;
+mod smoothing_params;
+mod autosync;
+mod lens_profiles;
+mod tiles;
 
 register_modules!(gyroflow);
@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use gyroflow_core::StabilizationManager;
+use gyroflow_core::gyro_source::{ TimeIMU, Quat64 };
+use lru::LruCache;
+
+const DEFAULT_BUDGET_MB: usize = 4096;
+const MB: usize = 1024 * 1024;
+// Footprint of a manager for a 4K clip, used to size the caches of the instances from the budget
+const TYPICAL_FOOTPRINT_MB: usize = 200;
+
+struct CacheEntry {
+    stab: Arc<StabilizationManager>,
+    footprint: usize,
+    users: usize,
+}
+
+// Global cache of loaded managers, bounded by the estimated memory footprint instead of the number of entries.
+// Entries not used by any instance are evicted first, then the least recently used ones.
+// The budget can be set with the GYROFLOW_OFX_CACHE_MB environment variable or the `ofxCacheBudgetMB` Gyroflow setting.
+pub struct ManagerCache {
+    entries: LruCache<String, CacheEntry>,
+    budget: usize,
+}
+
+impl ManagerCache {
+    pub fn new() -> Self {
+        let budget = Self::configured_budget_mb();
+        log::info!("Manager cache budget: {budget} MB");
+        Self {
+            entries: LruCache::unbounded(),
+            budget: budget * MB,
+        }
+    }
+
+    fn configured_budget_mb() -> usize {
+        if let Some(v) = std::env::var("GYROFLOW_OFX_CACHE_MB").ok().and_then(|x| x.parse::<usize>().ok()) {
+            return v;
+        }
+        gyroflow_core::settings::try_get("ofxCacheBudgetMB")
+            .and_then(|x| x.as_u64())
+            .map(|x| x as usize)
+            .unwrap_or(DEFAULT_BUDGET_MB)
+    }
+
+    // Rough estimate of the memory held by a manager: gyro samples, integrated and smoothed orientations,
+    // undistortion matrices of a frame (one per output row for rolling shutter) and the processing buffers
+    // The gyro samples are shared by the copies of a loaded project, they're counted for every copy to stay on the safe side
+    pub fn estimate_footprint(stab: &StabilizationManager) -> usize {
+        const BTREE_ENTRY: usize = std::mem::size_of::<i64>() + std::mem::size_of::<Quat64>() + 16;
+
+        let params = stab.params.read();
+        let gyro = stab.gyro.read();
+        let raw_imu = gyro.file_metadata.read().raw_imu.len() * std::mem::size_of::<TimeIMU>();
+        let orientations = (gyro.quaternions.len() + gyro.smoothed_quaternions.len()) * BTREE_ENTRY;
+        let undistortion = (params.output_size.1 + 1) * 12 * std::mem::size_of::<f32>();
+        // Input and output frames at the widest pixel format (RGBA f32)
+        let buffers = (params.size.0 * params.size.1 + params.output_size.0 * params.output_size.1) * 16;
+
+        raw_imu + orientations + undistortion + buffers
+    }
+
+    // Number of managers an instance keeps, the instances hold them only weakly so they're still bounded by the budget
+    pub fn instance_capacity(&self) -> std::num::NonZeroUsize {
+        std::num::NonZeroUsize::new((self.budget / (TYPICAL_FOOTPRINT_MB * MB)).clamp(2, 64)).unwrap()
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Arc<StabilizationManager>> {
+        self.entries.get(key).map(|x| x.stab.clone())
+    }
+
+    pub fn insert(&mut self, key: String, stab: Arc<StabilizationManager>) {
+        let footprint = Self::estimate_footprint(&stab);
+        self.insert_with_footprint(key, stab, footprint);
+    }
+
+    fn insert_with_footprint(&mut self, key: String, stab: Arc<StabilizationManager>, footprint: usize) {
+        let users = self.entries.peek(&key).map(|x| x.users).unwrap_or_default();
+        self.entries.put(key.clone(), CacheEntry { stab, footprint, users });
+        self.evict(&key);
+    }
+
+    // The footprint is estimated when an entry is inserted, this estimates it again after its manager was recomputed in place
+    pub fn update_footprint(&mut self, key: &str) {
+        let Some(v) = self.entries.peek_mut(key) else { return; };
+        v.footprint = Self::estimate_footprint(&v.stab);
+        self.evict(key);
+    }
+
+    // Instances register the keys they use, so unused entries can be evicted first
+    pub fn acquire(&mut self, key: &str) {
+        if let Some(v) = self.entries.peek_mut(key) {
+            v.users += 1;
+        }
+    }
+    pub fn release(&mut self, key: &str) {
+        if let Some(v) = self.entries.peek_mut(key) {
+            v.users = v.users.saturating_sub(1);
+        }
+    }
+    pub fn users(&self, key: &str) -> usize {
+        self.entries.peek(key).map(|x| x.users).unwrap_or_default()
+    }
+    // Drops the entry if no other instance uses it, so the next request loads it again
+    pub fn remove_unused(&mut self, key: &str) {
+        if self.entries.peek(key).map(|x| x.users == 0).unwrap_or_default() {
+            self.entries.pop(key);
+        }
+    }
+
+    // Drops all entries set up from the given file, regardless of their users. Their keys start with `{path}|`
+    pub fn remove_path(&mut self, path: &str) {
+        let prefix = format!("{path}|");
+        let keys = self.entries.iter().filter(|(k, _)| k.starts_with(&prefix)).map(|(k, _)| k.clone()).collect::<Vec<_>>();
+        for key in keys {
+            self.entries.pop(&key);
+        }
+    }
+
+    pub fn total_footprint(&self) -> usize {
+        self.entries.iter().map(|(_, v)| v.footprint).sum()
+    }
+
+    fn evict(&mut self, keep: &str) {
+        let mut total = self.total_footprint();
+        while total > self.budget {
+            // Iteration is from the most recently used, so the last match is the least recently used one
+            let candidate = self.entries.iter().filter(|(k, v)| k.as_str() != keep && v.users == 0).last()
+                .or_else(|| self.entries.iter().filter(|(k, _)| k.as_str() != keep).last())
+                .map(|(k, _)| k.clone());
+            let Some(key) = candidate else { break; };
+            if let Some(v) = self.entries.pop(&key) {
+                total -= v.footprint;
+                log::info!("Evicting manager {key} ({:.1} MB, {} users), cache: {:.1} / {} MB", v.footprint as f64 / MB as f64, v.users, total as f64 / MB as f64, self.budget / MB);
+            }
+        }
+    }
+}
+
+// Creates a copy of a loaded manager for an instance. The parsed telemetry is shared through the file metadata of the gyro source,
+// the parameters, smoothing, lens and keyframes are copied, so the instances can change them independently.
+// The stabilization itself isn't copied, it's set up for every copy from these
+pub fn derive_manager(base: &StabilizationManager) -> StabilizationManager {
+    let stab = StabilizationManager {
+        lens_profile_db: base.lens_profile_db.clone(),
+        ..Default::default()
+    };
+    *stab.gyro.write()       = base.gyro.read().clone();
+    *stab.lens.write()       = base.lens.read().clone();
+    *stab.smoothing.write()  = base.smoothing.read().clone();
+    *stab.params.write()     = base.params.read().clone();
+    *stab.keyframes.write()  = base.keyframes.read().clone();
+    *stab.input_file.write() = base.input_file.read().clone();
+    stab
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(budget_mb: usize) -> ManagerCache {
+        ManagerCache { entries: LruCache::unbounded(), budget: budget_mb * MB }
+    }
+    fn insert(cache: &mut ManagerCache, key: &str) {
+        cache.insert_with_footprint(key.to_owned(), Arc::new(StabilizationManager::default()), MB);
+    }
+    fn keys(cache: &ManagerCache) -> Vec<String> {
+        let mut keys = cache.entries.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn eviction_order() {
+        let mut cache = cache(3);
+        for key in ["a", "b", "c"] {
+            insert(&mut cache, key);
+        }
+        cache.acquire("c");
+        assert!(cache.get("a").is_some());
+
+        // Unused entries go first, the least recently used of them
+        insert(&mut cache, "d");
+        assert_eq!(keys(&cache), ["a", "c", "d"]);
+        assert_eq!(cache.total_footprint(), 3 * MB);
+
+        // Then the least recently used one, even if it's used
+        cache.acquire("a");
+        cache.acquire("d");
+        insert(&mut cache, "e");
+        assert_eq!(keys(&cache), ["a", "d", "e"]);
+
+        // Users are kept when the entry is replaced
+        insert(&mut cache, "a");
+        assert_eq!(cache.users("a"), 1);
+    }
+
+    #[test]
+    fn remove_unused() {
+        let mut cache = cache(8);
+        insert(&mut cache, "a");
+        cache.acquire("a");
+        cache.remove_unused("a");
+        assert!(cache.get("a").is_some());
+
+        cache.release("a");
+        cache.remove_unused("a");
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn remove_path() {
+        let mut cache = cache(8);
+        for key in ["/a.gyroflow|1", "/a.gyroflow|2", "/a.gyroflow.bak|1", "/b.gyroflow|1"] {
+            insert(&mut cache, key);
+        }
+        cache.acquire("/a.gyroflow|1");
+
+        // Regardless of the users, and only the keys of that file
+        cache.remove_path("/a.gyroflow");
+        assert_eq!(keys(&cache), ["/a.gyroflow.bak|1", "/b.gyroflow|1"]);
+    }
+}
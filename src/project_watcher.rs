@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::{ Arc, Weak };
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+use std::time::{ Duration, SystemTime };
+
+use parking_lot::Mutex;
+
+use super::background_job::RenderTrigger;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+// Files being watched, by path. Instances using the same project share the polling thread
+lazy_static::lazy_static! {
+    static ref WATCHED_FILES: Mutex<HashMap<String, Weak<WatchedFile>>> = Mutex::new(HashMap::new());
+}
+
+// Change flag of every instance watching the file, and the trigger to render it again
+struct WatchedFile {
+    subscribers: Mutex<Vec<(Weak<AtomicBool>, RenderTrigger)>>,
+}
+
+impl WatchedFile {
+    // Polls the modification time of the file in a background thread, until no instance is watching it
+    fn spawn(path: &str) -> Arc<Self> {
+        let file = Arc::new(Self { subscribers: Mutex::new(Vec::new()) });
+        let weak = Arc::downgrade(&file);
+        let path = path.to_owned();
+        std::thread::spawn(move || {
+            let stat = || std::fs::metadata(&path).ok().map(|md| (md.modified().unwrap_or(SystemTime::UNIX_EPOCH), md.len()));
+            let mut last = stat();
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let Some(file) = weak.upgrade() else { break; };
+                let current = stat();
+                // Ignore the file disappearing, it's usually replaced atomically by the save
+                if current.is_some() && current != last {
+                    log::info!("Project file changed: {path}");
+                    last = current;
+                    let triggers = {
+                        let mut subscribers = file.subscribers.lock();
+                        subscribers.retain(|(changed, _)| changed.strong_count() > 0);
+                        subscribers.iter().filter_map(|(changed, trigger)| {
+                            changed.upgrade()?.store(true, SeqCst);
+                            Some(trigger.clone())
+                        }).collect::<Vec<_>>()
+                    };
+                    for trigger in triggers {
+                        trigger.trigger();
+                    }
+                }
+            }
+            WATCHED_FILES.lock().retain(|_, v| v.strong_count() > 0);
+        });
+        file
+    }
+}
+
+// Watches the project file of an instance. The change is picked up on the next render, which is requested through the trigger
+pub struct ProjectWatcher {
+    pub path: String,
+    changed: Arc<AtomicBool>,
+    // Sync offsets saved in the file, to tell if it was synced again in Gyroflow
+    offsets: serde_json::Value,
+    _file: Arc<WatchedFile>,
+}
+
+impl ProjectWatcher {
+    pub fn watch(path: &str, trigger: &RenderTrigger) -> Self {
+        let file = {
+            let mut files = WATCHED_FILES.lock();
+            match files.get(path).and_then(Weak::upgrade) {
+                Some(file) => file,
+                None => {
+                    let file = WatchedFile::spawn(path);
+                    files.insert(path.to_owned(), Arc::downgrade(&file));
+                    file
+                }
+            }
+        };
+        let changed = Arc::new(AtomicBool::new(false));
+        file.subscribers.lock().push((Arc::downgrade(&changed), trigger.clone()));
+        Self { path: path.to_owned(), changed, offsets: Self::read_offsets(path), _file: file }
+    }
+
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, SeqCst)
+    }
+
+    // Reads the sync offsets from the changed file, returns true if they're different from the last read
+    pub fn take_offsets_changed(&mut self) -> bool {
+        let offsets = Self::read_offsets(&self.path);
+        let changed = offsets != self.offsets;
+        self.offsets = offsets;
+        changed
+    }
+
+    fn read_offsets(path: &str) -> serde_json::Value {
+        std::fs::read_to_string(path).ok()
+            .and_then(|x| serde_json::from_str::<serde_json::Value>(&x).ok())
+            .map(|x| x["offsets"].clone())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,97 @@
+use gyroflow_core::keyframes::KeyframeType;
+use gyroflow_core::smoothing::Smoothing;
+use ofx::*;
+
+// Parameters of the smoothing algorithms, read from their descriptions in gyroflow-core, so the plugin exposes the same ones as the Gyroflow app.
+// Each one is an OFX param named `Smoothing<Algorithm>_<name>`, and is enabled only when its algorithm is selected
+#[derive(Clone, Debug)]
+pub struct SmoothingParam {
+    pub algorithm: usize,
+    pub name: String,
+    pub ofx_name: String,
+    pub label: String,
+    pub is_checkbox: bool,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub keyframe: Option<KeyframeType>,
+}
+
+const SHARED_PARAMS: [&str; 5] = ["smoothness", "smoothness_pitch", "smoothness_yaw", "smoothness_roll", "per_axis"];
+
+pub fn has_parameter(smoothing: &Smoothing, name: &str) -> bool {
+    smoothing.current().get_parameters_json().as_array().map(|x| x.iter().any(|p| p["name"].as_str() == Some(name))).unwrap_or_default()
+}
+
+pub fn algorithm_names() -> Vec<String> {
+    Smoothing::default().get_names()
+}
+
+pub fn default_algorithm() -> usize {
+    let smoothing = Smoothing::default();
+    algorithm_index(&smoothing)
+}
+
+// Index of the algorithm in the current version of gyroflow-core, the choice param is stored by index which changes when algorithms are added
+pub fn algorithm_by_name(name: &str) -> Option<usize> {
+    algorithm_names().iter().position(|x| x == name)
+}
+
+pub fn algorithm_index(smoothing: &Smoothing) -> usize {
+    let current = smoothing.current().get_name();
+    smoothing.get_names().iter().position(|x| *x == current).unwrap_or_default()
+}
+
+pub fn all() -> Vec<SmoothingParam> {
+    let mut smoothing = Smoothing::default();
+    let mut ret = Vec::new();
+    for (algorithm, alg_name) in smoothing.get_names().into_iter().enumerate() {
+        smoothing.set_current(algorithm);
+        let alg_name = alg_name.chars().filter(|x| x.is_ascii_alphanumeric()).collect::<String>();
+        let params = smoothing.current().get_parameters_json();
+        for p in params.as_array().into_iter().flatten() {
+            let Some(name) = p["name"].as_str() else { continue; };
+            // Exposed as the `Smoothness` and per-axis params, which are shared by all algorithms
+            if SHARED_PARAMS.contains(&name) { continue; }
+
+            let default = p["default"].as_f64().or_else(|| p["value"].as_f64()).unwrap_or_default();
+            ret.push(SmoothingParam {
+                algorithm,
+                name:        name.to_owned(),
+                ofx_name:    format!("Smoothing{alg_name}_{name}"),
+                label:       p["description"].as_str().unwrap_or(name).to_owned(),
+                is_checkbox: p["type"].as_str() == Some("CheckBox"),
+                min:         p["from"].as_f64().unwrap_or(0.0),
+                max:         p["to"].as_f64().unwrap_or(default.max(1.0)),
+                default,
+                keyframe:    p["keyframe"].as_str().and_then(|x| x.parse::<KeyframeType>().ok()),
+            });
+        }
+    }
+    ret
+}
+
+pub enum SmoothingParamHandle {
+    Double(ParamHandle<Double>),
+    Bool(ParamHandle<Bool>),
+}
+impl SmoothingParamHandle {
+    pub fn get_value(&self) -> Result<f64> {
+        match self {
+            Self::Double(x) => x.get_value(),
+            Self::Bool(x) => Ok(if x.get_value()? { 1.0 } else { 0.0 }),
+        }
+    }
+    pub fn set_value(&self, v: f64) -> Result<()> {
+        match self {
+            Self::Double(x) => x.set_value(v),
+            Self::Bool(x) => x.set_value(v > 0.5),
+        }
+    }
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        match self {
+            Self::Double(x) => x.set_enabled(enabled),
+            Self::Bool(x) => x.set_enabled(enabled),
+        }
+    }
+}
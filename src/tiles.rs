@@ -0,0 +1,224 @@
+use std::ops::Range;
+
+use ofx::{ RectD, RectI };
+
+// CPU buffer of an image, `bounds` are in pixel coordinates.
+// `data` starts at the lowest address, the stride is negative when the rows go down in memory from the bottom one
+pub struct CpuImage<'a> {
+    pub data: &'a mut [u8],
+    pub stride: isize,
+    pub bounds: RectI,
+}
+
+// Whether the image covers only a part of its region of definition
+pub fn is_tile(bounds: &RectI, rod: &RectI) -> bool {
+    bounds.x1 != rod.x1 || bounds.x2 != rod.x2 || bounds.y1 != rod.y1 || bounds.y2 != rod.y2
+}
+
+// Byte ranges of the rows of the part which is in both images, they are placed by their bounds
+fn common_rows(a: &CpuImage, b: &CpuImage, bytes_per_pixel: usize) -> impl Iterator<Item = (Range<usize>, Range<usize>)> {
+    let (x1, x2) = (a.bounds.x1.max(b.bounds.x1), a.bounds.x2.min(b.bounds.x2));
+    let (y1, y2) = (a.bounds.y1.max(b.bounds.y1), a.bounds.y2.min(b.bounds.y2));
+    let len = if x1 < x2 { (x2 - x1) as usize * bytes_per_pixel } else { 0 };
+    // Byte offset of the start of the row `y`, counted from the lowest row in memory
+    let row = |image: &CpuImage| {
+        let (bounds, stride) = (image.bounds, image.stride);
+        move |y: i32| if stride < 0 { (bounds.y2 - 1 - y) as usize * stride.unsigned_abs() } else { (y - bounds.y1) as usize * stride as usize }
+    };
+    let (a_row, b_row) = (row(a), row(b));
+    let (ax, bx, a_len, b_len) = (a.bounds.x1, b.bounds.x1, a.data.len(), b.data.len());
+    (y1..y2).filter(move |_| len > 0).map(move |y| {
+        let s = a_row(y) + (x1 - ax) as usize * bytes_per_pixel;
+        let d = b_row(y) + (x1 - bx) as usize * bytes_per_pixel;
+        (s..s + len, d..d + len)
+    }).filter(move |(s, d)| s.end <= a_len && d.end <= b_len)
+}
+
+// Copies the part which is in both images
+pub fn copy_region(src: &CpuImage, dst: &mut CpuImage, bytes_per_pixel: usize) {
+    let rows = common_rows(src, dst, bytes_per_pixel).collect::<Vec<_>>();
+    for (s, d) in rows {
+        dst.data[d].copy_from_slice(&src.data[s]);
+    }
+}
+
+// Whether the part which is in both images has the same pixels
+pub fn same_region(a: &CpuImage, b: &CpuImage, bytes_per_pixel: usize) -> bool {
+    common_rows(a, b, bytes_per_pixel).all(|(ra, rb)| a.data[ra] == b.data[rb])
+}
+
+// Whole frame for the stabilization, when the host renders in tiles. Only the copied regions have any pixels
+pub struct FullFrame {
+    pub data: Vec<u8>,
+    pub stride: usize,
+    pub rod: RectI,
+}
+impl FullFrame {
+    pub fn new(rod: RectI, bytes_per_pixel: usize) -> Self {
+        let mut frame = Self { data: Vec::new(), stride: 0, rod };
+        frame.reset(rod, bytes_per_pixel);
+        frame
+    }
+    // Clears the frame for another region, the buffer is reused
+    pub fn reset(&mut self, rod: RectI, bytes_per_pixel: usize) {
+        self.stride = (rod.x2 - rod.x1) as usize * bytes_per_pixel;
+        self.rod = rod;
+        self.data.clear();
+        self.data.resize(self.stride * (rod.y2 - rod.y1) as usize, 0);
+    }
+    pub fn image(&mut self) -> CpuImage<'_> {
+        CpuImage { data: &mut self.data, stride: self.stride as isize, bounds: self.rod }
+    }
+}
+
+// The stabilization can only render whole frames, so a tiled frame is rendered once and its tiles are copied from it.
+// `key` identifies the rendered frame, it's empty until the render is done
+pub struct TiledRender {
+    pub key: String,
+    pub source: FullFrame,
+    pub output: FullFrame,
+}
+impl TiledRender {
+    pub fn new(source_rod: RectI, output_rod: RectI, bytes_per_pixel: usize) -> Self {
+        Self {
+            key:    String::new(),
+            source: FullFrame::new(source_rod, bytes_per_pixel),
+            output: FullFrame::new(output_rod, bytes_per_pixel),
+        }
+    }
+}
+
+// Points of a grid with `grid` cells per axis over the video, row by row from the top
+pub fn grid_points(grid: usize, video_size: (usize, usize)) -> Vec<(f32, f32)> {
+    let (cell_w, cell_h) = (video_size.0 as f64 / grid as f64, video_size.1 as f64 / grid as f64);
+    (0..=grid).flat_map(|y| (0..=grid).map(move |x| ((x as f64 * cell_w) as f32, (y as f64 * cell_h) as f32))).collect()
+}
+
+// Bounding rect (x1, y1, x2, y2) in video pixels from the top, of the grid cells which land in `region`.
+// `mapped` are the `grid_points` mapped through the stabilization, and `to_region` converts them to the coordinates of `region`
+pub fn visible_video_rect(mapped: &[(f32, f32)], grid: usize, video_size: (usize, usize), to_region: impl Fn((f32, f32)) -> (f64, f64), region: &RectD) -> Option<(f64, f64, f64, f64)> {
+    let (cell_w, cell_h) = (video_size.0 as f64 / grid as f64, video_size.1 as f64 / grid as f64);
+    let mut video_rect: Option<(f64, f64, f64, f64)> = None;
+    for y in 0..grid {
+        for x in 0..grid {
+            let corners = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)].map(|(cx, cy)| mapped[cy * (grid + 1) + cx]);
+            if corners.iter().any(|p| !p.0.is_finite() || !p.1.is_finite()) { continue; }
+            let corners = corners.map(&to_region);
+            let (x1, x2) = corners.iter().fold((f64::MAX, f64::MIN), |a, p| (a.0.min(p.0), a.1.max(p.0)));
+            let (y1, y2) = corners.iter().fold((f64::MAX, f64::MIN), |a, p| (a.0.min(p.1), a.1.max(p.1)));
+            if x2 < region.x1 || x1 > region.x2 || y2 < region.y1 || y1 > region.y2 { continue; }
+
+            let cell = (x as f64 * cell_w, y as f64 * cell_h, (x + 1) as f64 * cell_w, (y + 1) as f64 * cell_h);
+            video_rect = Some(match video_rect {
+                Some(r) => (r.0.min(cell.0), r.1.min(cell.1), r.2.max(cell.2), r.3.max(cell.3)),
+                None => cell
+            });
+        }
+    }
+    video_rect
+}
+
+// Bounding rect (x1, y1, x2, y2) of `rect` rotated around `center` by `degrees` in either direction
+pub fn rotated_bounds(rect: (f64, f64, f64, f64), center: (f64, f64), degrees: f64) -> (f64, f64, f64, f64) {
+    let corners = [(rect.0, rect.1), (rect.2, rect.1), (rect.0, rect.3), (rect.2, rect.3)];
+    let mut bounds = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for angle in [degrees, -degrees] {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        for (x, y) in corners {
+            let (dx, dy) = (x - center.0, y - center.1);
+            let (rx, ry) = (center.0 + dx * cos - dy * sin, center.1 + dx * sin + dy * cos);
+            bounds = (bounds.0.min(rx), bounds.1.min(ry), bounds.2.max(rx), bounds.3.max(ry));
+        }
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x1: i32, y1: i32, x2: i32, y2: i32) -> RectI { RectI { x1, y1, x2, y2 } }
+
+    #[test]
+    fn tiles() {
+        assert!(!is_tile(&rect(0, 0, 16, 8), &rect(0, 0, 16, 8)));
+        assert!(is_tile(&rect(0, 4, 16, 8), &rect(0, 0, 16, 8)));
+        assert!(is_tile(&rect(2, 0, 16, 8), &rect(0, 0, 16, 8)));
+    }
+
+    #[test]
+    fn copy_and_compare_regions() {
+        // 4x4 frame with one byte per pixel, and a 2x2 tile at (1, 2)
+        let mut frame = FullFrame::new(rect(0, 0, 4, 4), 1);
+        let mut tile_data = vec![1, 2, 3, 4];
+        let tile = CpuImage { data: &mut tile_data, stride: 2, bounds: rect(1, 2, 3, 4) };
+        copy_region(&tile, &mut frame.image(), 1);
+        assert_eq!(frame.data, [0, 0, 0, 0,  0, 0, 0, 0,  0, 1, 2, 0,  0, 3, 4, 0]);
+        assert!(same_region(&tile, &frame.image(), 1));
+
+        // Only the part inside the destination is copied
+        let mut out_data = vec![0; 4];
+        let mut out = CpuImage { data: &mut out_data, stride: 2, bounds: rect(2, 3, 4, 5) };
+        copy_region(&frame.image(), &mut out, 1);
+        assert_eq!(out_data, [4, 0, 0, 0]);
+
+        let mut other_data = vec![1, 2, 3, 5];
+        let other = CpuImage { data: &mut other_data, stride: 2, bounds: rect(1, 2, 3, 4) };
+        assert!(!same_region(&other, &frame.image(), 1));
+
+        // Strides with padding and more bytes per pixel
+        let mut padded_data = vec![7, 8, 9, 9,  5, 6, 9, 9];
+        let padded = CpuImage { data: &mut padded_data, stride: 4, bounds: rect(3, 0, 4, 2) };
+        let mut frame = FullFrame::new(rect(0, 0, 4, 2), 2);
+        copy_region(&padded, &mut frame.image(), 2);
+        assert_eq!(frame.data, [0, 0, 0, 0, 0, 0, 7, 8,  0, 0, 0, 0, 0, 0, 5, 6]);
+
+        // Rows going down in memory, the top row is first
+        let mut top_down_data = vec![3, 4,  1, 2];
+        let top_down = CpuImage { data: &mut top_down_data, stride: -2, bounds: rect(1, 2, 3, 4) };
+        let mut frame = FullFrame::new(rect(0, 0, 4, 4), 1);
+        copy_region(&top_down, &mut frame.image(), 1);
+        assert_eq!(frame.data, [0, 0, 0, 0,  0, 0, 0, 0,  0, 1, 2, 0,  0, 3, 4, 0]);
+        let mut out_data = vec![0; 4];
+        copy_region(&frame.image(), &mut CpuImage { data: &mut out_data, stride: -2, bounds: rect(1, 2, 3, 4) }, 1);
+        assert_eq!(out_data, [3, 4, 1, 2]);
+
+        // Reused for another frame
+        let mut frame = FullFrame::new(rect(0, 0, 4, 2), 2);
+        frame.reset(rect(0, 0, 1, 1), 2);
+        assert_eq!(frame.data, [0, 0]);
+        assert_eq!(frame.stride, 2);
+    }
+
+    #[test]
+    fn visible_video() {
+        let size = (160, 80);
+        let points = grid_points(4, size);
+        assert_eq!(points.len(), 25);
+        assert_eq!(points[6], (40.0, 20.0));
+
+        // Without any stabilization the visible part is the region itself, in whole cells
+        let region = RectD { x1: 50.0, y1: 10.0, x2: 70.0, y2: 30.0 };
+        assert_eq!(visible_video_rect(&points, 4, size, |p| (p.0 as f64, p.1 as f64), &region), Some((40.0, 0.0, 80.0, 40.0)));
+
+        // Zoomed in 2x around the center, the corner of the output shows the cells around the center
+        let zoom = |p: (f32, f32)| ((p.0 as f64 - 80.0) * 2.0 + 80.0, (p.1 as f64 - 40.0) * 2.0 + 40.0);
+        let region = RectD { x1: 1.0, y1: 1.0, x2: 10.0, y2: 10.0 };
+        assert_eq!(visible_video_rect(&points, 4, size, zoom, &region), Some((40.0, 20.0, 80.0, 40.0)));
+
+        // Points which couldn't be mapped are skipped
+        let outside = RectD { x1: 1000.0, y1: 1000.0, x2: 1010.0, y2: 1010.0 };
+        assert_eq!(visible_video_rect(&points, 4, size, |p| (p.0 as f64, p.1 as f64), &outside), None);
+        let invalid = vec![(f32::NAN, f32::NAN); points.len()];
+        assert_eq!(visible_video_rect(&invalid, 4, size, |p| (p.0 as f64, p.1 as f64), &region), None);
+    }
+
+    #[test]
+    fn rotated_rect() {
+        let near = |a: (f64, f64, f64, f64), b: (f64, f64, f64, f64)| (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9 && (a.2 - b.2).abs() < 1e-9 && (a.3 - b.3).abs() < 1e-9;
+        assert!(near(rotated_bounds((0.0, 0.0, 4.0, 2.0), (2.0, 1.0), 0.0), (0.0, 0.0, 4.0, 2.0)));
+        assert!(near(rotated_bounds((0.0, 0.0, 4.0, 2.0), (2.0, 1.0), 90.0), (1.0, -1.0, 3.0, 3.0)));
+        // Both directions are included, when the rect isn't centered
+        assert!(near(rotated_bounds((0.0, 0.0, 2.0, 2.0), (0.0, 0.0), 90.0), (-2.0, -2.0, 2.0, 2.0)));
+    }
+}
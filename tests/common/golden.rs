@@ -0,0 +1,99 @@
+// Reference image storage for the golden-image tests.
+// References are 16-bit RGBA PAM files (viewable with most image tools) in tests/golden.
+// Run the tests with GYROFLOW_OFX_BLESS=1 to (re)generate them after an intentional output change.
+
+use std::path::PathBuf;
+use super::Frame;
+
+pub fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+pub fn is_blessing() -> bool {
+    std::env::var("GYROFLOW_OFX_BLESS").map(|x| x == "1").unwrap_or_default()
+}
+
+pub struct Reference {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<f32>,
+}
+impl Reference {
+    pub fn from_frame(frame: &Frame) -> Self {
+        Self { width: frame.width, height: frame.height, data: frame.to_f32() }
+    }
+
+    pub fn to_pam(&self) -> Vec<u8> {
+        let mut out = format!("P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 65535\nTUPLTYPE RGB_ALPHA\nENDHDR\n", self.width, self.height).into_bytes();
+        // PAM rows are top-down, OFX rows are bottom-up
+        for y in (0..self.height).rev() {
+            for v in &self.data[y * self.width * 4..(y + 1) * self.width * 4] {
+                out.extend_from_slice(&((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes());
+            }
+        }
+        out
+    }
+    pub fn from_pam(bytes: &[u8]) -> Option<Self> {
+        let header_end = bytes.windows(7).position(|x| x == b"ENDHDR\n")? + 7;
+        let header = std::str::from_utf8(&bytes[..header_end]).ok()?;
+        let field = |name: &str| header.lines().find_map(|l| l.strip_prefix(name)).and_then(|x| x.trim().parse::<usize>().ok());
+        let (width, height) = (field("WIDTH ")?, field("HEIGHT ")?);
+        if field("DEPTH ")? != 4 || field("MAXVAL ")? != 65535 { return None; }
+
+        let body = &bytes[header_end..];
+        if body.len() != width * height * 4 * 2 { return None; }
+        let mut data = vec![0.0f32; width * height * 4];
+        for y in 0..height {
+            let src = &body[(height - 1 - y) * width * 8..(height - y) * width * 8];
+            for (i, px) in src.chunks_exact(2).enumerate() {
+                data[y * width * 4 + i] = u16::from_be_bytes([px[0], px[1]]) as f32 / 65535.0;
+            }
+        }
+        Some(Self { width, height, data })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Maximum mean absolute difference over all channels
+    pub mean: f32,
+    /// A sample differing by more than this is counted as an outlier
+    pub outlier: f32,
+    /// Maximum fraction of outlier samples
+    pub max_outliers: f32,
+}
+
+/// Compares `frame` with the stored reference `name`, or stores it when blessing
+pub fn check(name: &str, frame: &Frame, tolerance: Tolerance) -> Result<(), String> {
+    let path = golden_dir().join(format!("{name}.pam"));
+    let actual = Reference::from_frame(frame);
+    if is_blessing() {
+        std::fs::create_dir_all(golden_dir()).map_err(|e| e.to_string())?;
+        std::fs::write(&path, actual.to_pam()).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let expected = std::fs::read(&path)
+        .ok()
+        .and_then(|x| Reference::from_pam(&x))
+        .ok_or_else(|| format!("Missing or invalid reference {path:?}, run the tests with GYROFLOW_OFX_BLESS=1 to create it"))?;
+
+    let result = if (expected.width, expected.height) != (actual.width, actual.height) {
+        Err(format!("{name}: size {}x{} doesn't match the reference {}x{}", actual.width, actual.height, expected.width, expected.height))
+    } else {
+        let diffs = expected.data.iter().zip(&actual.data).map(|(a, b)| (a - b).abs()).collect::<Vec<_>>();
+        let mean = diffs.iter().sum::<f32>() / diffs.len() as f32;
+        let outliers = diffs.iter().filter(|x| **x > tolerance.outlier).count() as f32 / diffs.len() as f32;
+        if mean > tolerance.mean || outliers > tolerance.max_outliers {
+            Err(format!("{name}: mean difference {mean:.5} (max {:.5}), outliers {:.3}% (max {:.3}%)", tolerance.mean, outliers * 100.0, tolerance.max_outliers * 100.0))
+        } else {
+            Ok(())
+        }
+    };
+    if result.is_err() {
+        // Keep the actual output around for inspection
+        let actual_path = std::env::temp_dir().join("gyroflow-ofx-tests").join(format!("{name}.actual.pam"));
+        let _ = std::fs::create_dir_all(actual_path.parent().unwrap());
+        let _ = std::fs::write(&actual_path, actual.to_pam());
+    }
+    result
+}
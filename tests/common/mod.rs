@@ -0,0 +1,526 @@
+// Headless OpenFX host used by the integration tests.
+// It loads the `gyroflow_ofx` cdylib built by cargo, calls `OfxGetPlugin` and drives the plugin actions on CPU buffers.
+// Run with GYROFLOW_OFX_LIB=/path/to/plugin to test a different binary.
+
+#![allow(dead_code)]
+
+pub mod suites;
+pub mod project;
+pub mod golden;
+
+use std::ffi::{ c_char, c_int, c_void, CString };
+use std::sync::{ Mutex, MutexGuard, OnceLock };
+use suites::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitDepth { Byte, Short, Half, Float }
+impl BitDepth {
+    pub fn bytes(&self) -> usize { match self { Self::Byte => 1, Self::Short | Self::Half => 2, Self::Float => 4 } }
+    pub fn ofx_name(&self) -> &'static str {
+        match self { Self::Byte => "OfxBitDepthByte", Self::Short => "OfxBitDepthShort", Self::Half => "OfxBitDepthHalf", Self::Float => "OfxBitDepthFloat" }
+    }
+}
+
+/// Interleaved RGBA, RGB or Alpha frame with bottom-up rows, like OFX hosts hand them to plugins
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub depth: BitDepth,
+    pub channels: usize,
+    pub data: Vec<u8>,
+}
+impl Frame {
+    pub fn new(width: usize, height: usize, depth: BitDepth) -> Self {
+        Self::with_channels(width, height, depth, 4)
+    }
+    pub fn with_channels(width: usize, height: usize, depth: BitDepth, channels: usize) -> Self {
+        Self { width, height, depth, channels, data: vec![0u8; width * height * channels * depth.bytes()] }
+    }
+    pub fn row_bytes(&self) -> usize { self.width * self.channels * self.depth.bytes() }
+    pub fn components(&self) -> &'static str {
+        match self.channels { 1 => "OfxImageComponentAlpha", 3 => "OfxImageComponentRGB", _ => "OfxImageComponentRGBA" }
+    }
+    // Indices of the stored channels in an RGBA pixel
+    fn channel_map(&self) -> &'static [usize] {
+        match self.channels { 1 => &[3], 3 => &[0, 1, 2], _ => &[0, 1, 2, 3] }
+    }
+
+    /// Same pixels with a different number of channels, missing ones are opaque black
+    pub fn to_channels(&self, channels: usize) -> Self {
+        let mut frame = Self::with_channels(self.width, self.height, self.depth, channels);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                frame.set_pixel(x, y, self.pixel(x, y));
+            }
+        }
+        frame
+    }
+
+    /// Deterministic test pattern: a checkerboard with color gradients, so both geometry and values are visible in the output
+    pub fn pattern(width: usize, height: usize, depth: BitDepth) -> Self {
+        let mut frame = Self::new(width, height, depth);
+        for y in 0..height {
+            for x in 0..width {
+                let checker = ((x / 16) + (y / 16)) % 2 == 0;
+                let px = [
+                    x as f32 / width as f32,
+                    y as f32 / height as f32,
+                    if checker { 0.9 } else { 0.1 },
+                    1.0
+                ];
+                frame.set_pixel(x, y, px);
+            }
+        }
+        frame
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, px: [f32; 4]) {
+        let bpc = self.depth.bytes();
+        let offs = y * self.row_bytes() + x * self.channels * bpc;
+        for (c, &i) in self.channel_map().iter().enumerate() {
+            let (o, v) = (offs + c * bpc, &px[i]);
+            match self.depth {
+                BitDepth::Byte  => self.data[o] = (v.clamp(0.0, 1.0) * 255.0).round() as u8,
+                BitDepth::Short => self.data[o..o + 2].copy_from_slice(&((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_ne_bytes()),
+                BitDepth::Half  => self.data[o..o + 2].copy_from_slice(&f32_to_f16(*v).to_ne_bytes()),
+                BitDepth::Float => self.data[o..o + 4].copy_from_slice(&v.to_ne_bytes()),
+            }
+        }
+    }
+    pub fn pixel(&self, x: usize, y: usize) -> [f32; 4] {
+        let bpc = self.depth.bytes();
+        let offs = y * self.row_bytes() + x * self.channels * bpc;
+        let mut px = [0.0, 0.0, 0.0, 1.0f32];
+        for (c, &i) in self.channel_map().iter().enumerate() {
+            let o = offs + c * bpc;
+            px[i] = match self.depth {
+                BitDepth::Byte  => self.data[o] as f32 / 255.0,
+                BitDepth::Short => u16::from_ne_bytes([self.data[o], self.data[o + 1]]) as f32 / 65535.0,
+                BitDepth::Half  => f16_to_f32(u16::from_ne_bytes([self.data[o], self.data[o + 1]])),
+                BitDepth::Float => f32::from_ne_bytes([self.data[o], self.data[o + 1], self.data[o + 2], self.data[o + 3]]),
+            };
+        }
+        px
+    }
+    /// Part of the frame in `[x1, y1, x2, y2]`
+    pub fn crop(&self, rect: [usize; 4]) -> Self {
+        let mut frame = Self::with_channels(rect[2] - rect[0], rect[3] - rect[1], self.depth, self.channels);
+        let (offs, len) = (rect[0] * self.channels * self.depth.bytes(), frame.row_bytes());
+        for y in 0..frame.height {
+            let src = (rect[1] + y) * self.row_bytes() + offs;
+            frame.data[y * len..(y + 1) * len].copy_from_slice(&self.data[src..src + len]);
+        }
+        frame
+    }
+    /// Reverses the order of the rows in memory
+    pub fn flip_rows(&mut self) {
+        let len = self.row_bytes();
+        if len == 0 { return; }
+        self.data = self.data.chunks(len).rev().flatten().copied().collect();
+    }
+    pub fn to_f32(&self) -> Vec<f32> {
+        (0..self.height).flat_map(|y| (0..self.width).flat_map(move |x| self.pixel(x, y))).collect()
+    }
+}
+
+pub fn f32_to_f16(v: f32) -> u16 {
+    half::f16::from_f32(v).to_bits()
+}
+pub fn f16_to_f32(v: u16) -> f32 {
+    half::f16::from_bits(v).to_f32()
+}
+
+pub struct Host {
+    _lib: libloading::Library,
+    plugin: *const OfxPlugin,
+    host: Box<OfxHost>,
+    host_props: Box<PropertySet>,
+    descriptor: Box<Effect>,
+}
+// The plugin is only ever driven from behind the global mutex
+unsafe impl Send for Host { }
+
+impl Host {
+    pub fn plugin_path() -> std::path::PathBuf {
+        if let Ok(path) = std::env::var("GYROFLOW_OFX_LIB") {
+            return path.into();
+        }
+        // target/<profile>/deps/<test binary> -> target/<profile>/<cdylib>
+        let exe = std::env::current_exe().unwrap();
+        let dir = exe.parent().and_then(|x| x.parent()).unwrap();
+        dir.join(format!("{}gyroflow_ofx{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX))
+    }
+
+    /// Loads and describes the plugin once per test binary. The plugin keeps global state, so tests are serialized on this lock.
+    pub fn get() -> MutexGuard<'static, Host> {
+        static HOST: OnceLock<Mutex<Host>> = OnceLock::new();
+        HOST.get_or_init(|| Mutex::new(Host::load()))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn load() -> Self {
+        let path = Self::plugin_path();
+        let lib = unsafe { libloading::Library::new(&path) }.unwrap_or_else(|e| panic!("Failed to load {path:?}: {e:?}"));
+
+        let plugin = unsafe {
+            let num_plugins = lib.get::<unsafe extern "C" fn() -> c_int>(b"OfxGetNumberOfPlugins\0").unwrap();
+            assert_eq!(num_plugins(), 1);
+            let get_plugin = lib.get::<unsafe extern "C" fn(c_int) -> *const OfxPlugin>(b"OfxGetPlugin\0").unwrap();
+            get_plugin(0)
+        };
+        assert!(!plugin.is_null());
+
+        let mut host_props = Box::new(PropertySet::default());
+        host_props.set_str   ("OfxPropName", "com.gyroflow.testhost");
+        host_props.set_str   ("OfxPropLabel", "Gyroflow test host");
+        host_props.set_ints  ("OfxPropAPIVersion", &[1, 4]);
+        host_props.set_ints  ("OfxPropVersion", &[1, 0, 0]);
+        host_props.set_str   ("OfxPropVersionLabel", "1.0.0");
+        host_props.set_int   ("OfxImageEffectHostPropIsBackground", 1);
+        host_props.set_int   ("OfxImageEffectPropSupportsOverlays", 0);
+        host_props.set_int   ("OfxImageEffectPropSupportsMultiResolution", 1);
+        host_props.set_int   ("OfxImageEffectPropSupportsTiles", 1);
+        host_props.set_int   ("OfxImageEffectPropTemporalClipAccess", 1);
+        host_props.set_strs  ("OfxImageEffectPropSupportedComponents", &["OfxImageComponentRGBA", "OfxImageComponentRGB", "OfxImageComponentAlpha"]);
+        host_props.set_strs  ("OfxImageEffectPropSupportedContexts", &["OfxImageEffectContextFilter"]);
+        host_props.set_strs  ("OfxImageEffectPropSupportedPixelDepths", &["OfxBitDepthByte", "OfxBitDepthShort", "OfxBitDepthHalf", "OfxBitDepthFloat"]);
+        host_props.set_int   ("OfxImageEffectPropSupportsMultipleClipDepths", 1);
+        host_props.set_int   ("OfxImageEffectPropSupportsMultipleClipPARs", 0);
+        host_props.set_int   ("OfxImageEffectPropSetableFrameRate", 0);
+        host_props.set_int   ("OfxImageEffectPropSetableFielding", 0);
+        host_props.set_int   ("OfxParamHostPropSupportsCustomInteract", 0);
+        host_props.set_int   ("OfxParamHostPropSupportsStringAnimation", 0);
+        host_props.set_int   ("OfxParamHostPropSupportsChoiceAnimation", 0);
+        host_props.set_int   ("OfxParamHostPropSupportsBooleanAnimation", 0);
+        host_props.set_int   ("OfxParamHostPropSupportsCustomAnimation", 0);
+        host_props.set_int   ("OfxParamHostPropMaxParameters", -1);
+        host_props.set_int   ("OfxParamHostPropMaxPages", 0);
+        host_props.set_ints  ("OfxParamHostPropPageRowColumnCount", &[0, 0]);
+        host_props.set_int   ("OfxParamHostPropSupportsParametricAnimation", 0);
+        // CPU only
+        host_props.set_str   ("OfxImageEffectPropOpenGLRenderSupported", "false");
+        host_props.set_str   ("OfxImageEffectPropOpenCLRenderSupported", "false");
+        host_props.set_str   ("OfxImageEffectPropCudaRenderSupported",   "false");
+        host_props.set_str   ("OfxImageEffectPropMetalRenderSupported",  "false");
+
+        let host = Box::new(OfxHost {
+            host: &mut *host_props as *mut _ as Handle,
+            fetchSuite: fetch_suite,
+        });
+
+        let mut this = Self {
+            _lib: lib,
+            plugin,
+            host,
+            host_props,
+            descriptor: Box::default(),
+        };
+        unsafe {
+            (*plugin).setHost.expect("setHost")(&mut *this.host);
+        }
+
+        assert_eq!(this.action("OfxActionLoad", std::ptr::null_mut(), None, None), STAT_OK);
+
+        let mut descriptor = Box::new(Effect::default());
+        descriptor.props.set_str("OfxPropType", "OfxTypeImageEffect");
+        descriptor.props.set_str("OfxPluginPropFilePath", &std::env::temp_dir().join("gyroflow-ofx-test").to_string_lossy());
+        let handle = &mut *descriptor as *mut Effect as Handle;
+        assert_eq!(this.action("OfxActionDescribe", handle, None, None), STAT_OK);
+
+        let mut in_args = PropertySet::default();
+        in_args.set_str("OfxImageEffectPropContext", "OfxImageEffectContextFilter");
+        assert_eq!(this.action("OfxImageEffectActionDescribeInContext", handle, Some(&mut in_args), None), STAT_OK);
+        this.descriptor = descriptor;
+
+        this
+    }
+
+    pub fn plugin_identifier(&self) -> String {
+        unsafe { std::ffi::CStr::from_ptr((*self.plugin).pluginIdentifier).to_string_lossy().to_string() }
+    }
+    pub fn descriptor(&self) -> &Effect { &self.descriptor }
+
+    pub fn action(&self, action: &str, handle: Handle, in_args: Option<&mut PropertySet>, out_args: Option<&mut PropertySet>) -> c_int {
+        let action = CString::new(action).unwrap();
+        let in_args  = in_args .map(|x| x as *mut _ as Handle).unwrap_or(std::ptr::null_mut());
+        let out_args = out_args.map(|x| x as *mut _ as Handle).unwrap_or(std::ptr::null_mut());
+        unsafe {
+            (*self.plugin).mainEntry.expect("mainEntry")(action.as_ptr() as *const c_char, handle as *const c_void, in_args, out_args)
+        }
+    }
+
+    /// Creates an instance in the filter context with `frame` connected as the source clip
+    pub fn create_instance(&self, frame: &Frame, fps: f64, num_frames: usize) -> Instance<'_> {
+        let mut effect = self.descriptor.clone();
+        for p in effect.params.params.iter_mut() {
+            p.reset_to_default();
+        }
+        effect.props.set_str("OfxPropType", "OfxTypeImageEffectInstance");
+        effect.props.set_str("OfxImageEffectPropContext", "OfxImageEffectContextFilter");
+        effect.props.set_int("OfxPropIsInteractive", 0);
+        effect.props.set_double("OfxImageEffectInstancePropEffectDuration", num_frames as f64);
+        effect.props.set_double("OfxImageEffectPropFrameRate", fps);
+        effect.props.set_doubles("OfxImageEffectPropProjectSize", &[frame.width as f64, frame.height as f64]);
+        effect.props.set_doubles("OfxImageEffectPropProjectExtent", &[frame.width as f64, frame.height as f64]);
+        effect.props.set_doubles("OfxImageEffectPropProjectOffset", &[0.0, 0.0]);
+        effect.props.set_double("OfxImageEffectPropProjectPixelAspectRatio", 1.0);
+        effect.props.set_int("OfxImageEffectInstancePropSequentialRender", 0);
+
+        for clip in effect.clips.iter_mut() {
+            clip.props.set_int("OfxImageClipPropConnected", 1);
+            clip.props.set_double("OfxImageEffectPropFrameRate", fps);
+            clip.props.set_doubles("OfxImageEffectPropFrameRange", &[0.0, (num_frames - 1) as f64]);
+            clip.props.set_double("OfxImageEffectPropUnmappedFrameRate", fps);
+            clip.props.set_doubles("OfxImageEffectPropUnmappedFrameRange", &[0.0, (num_frames - 1) as f64]);
+            clip.props.set_str("OfxImageClipPropFieldOrder", "OfxImageFieldNone");
+            clip.props.set_double("OfxImagePropPixelAspectRatio", 1.0);
+            clip.props.set_str("OfxImageEffectPropPreMultiplication", "OfxImageUnPreMultiplied");
+            clip.props.set_int("OfxImageClipPropContinuousSamples", 0);
+        }
+
+        let mut instance = Instance { host: self, effect: Box::new(effect), source: frame.clone(), interactive: false, draft: false, top_down: false };
+        instance.set_source(frame.clone());
+        let handle = instance.handle();
+        assert_eq!(self.action("OfxActionCreateInstance", handle, None, None), STAT_OK);
+        instance
+    }
+}
+
+pub struct Instance<'a> {
+    host: &'a Host,
+    effect: Box<Effect>,
+    source: Frame,
+    /// Renders as if the user was scrubbing the timeline, instead of a final render
+    pub interactive: bool,
+    /// Renders with the host's draft quality flag set
+    pub draft: bool,
+    /// Images set from now on have the top row first in memory, with negative row bytes like some hosts use
+    pub top_down: bool,
+}
+impl Instance<'_> {
+    pub fn handle(&mut self) -> Handle { &mut *self.effect as *mut Effect as Handle }
+    pub fn effect(&self) -> &Effect { &self.effect }
+
+    /// Creates the instance again from its param values, like a host opening a saved project
+    pub fn reopen(&mut self) {
+        let handle = self.handle();
+        assert_eq!(self.host.action("OfxActionDestroyInstance", handle, None, None), STAT_OK);
+        assert_eq!(self.host.action("OfxActionCreateInstance", handle, None, None), STAT_OK);
+    }
+
+    fn set_clip_frame(&mut self, clip: &str, frame: Frame) {
+        let size = (frame.width, frame.height);
+        self.set_clip_tile(clip, frame, [0, 0, size.0, size.1], size);
+    }
+    /// Sets the image of a clip to `frame`, which covers `bounds` of a frame of `size`
+    fn set_clip_tile(&mut self, clip: &str, mut frame: Frame, bounds: [usize; 4], size: (usize, usize)) {
+        let clip = self.effect.clip_mut(clip).unwrap();
+        let (w, h) = (size.0 as f64, size.1 as f64);
+        let mut row_bytes = frame.row_bytes() as c_int;
+        // The data points to the bottom row either way
+        let mut data = frame.data.as_mut_ptr();
+        if self.top_down && frame.height > 0 {
+            frame.flip_rows();
+            data = unsafe { frame.data.as_mut_ptr().add((frame.height - 1) * frame.row_bytes()) };
+            row_bytes = -row_bytes;
+        }
+        let mut image = Box::new(Image::default());
+        image.props.set_str("OfxPropType", "OfxTypeImage");
+        image.props.set_ptr("OfxImagePropData", data as Handle);
+        image.props.set_ints("OfxImagePropBounds", &bounds.map(|x| x as c_int));
+        image.props.set_doubles("OfxImagePropRegionOfDefinition", &[0.0, 0.0, w, h]);
+        image.props.set_int("OfxImagePropRowBytes", row_bytes);
+        image.props.set_str("OfxImageEffectPropPixelDepth", frame.depth.ofx_name());
+        image.props.set_str("OfxImageEffectPropComponents", frame.components());
+        image.props.set_doubles("OfxImageEffectPropRenderScale", &[1.0, 1.0]);
+        image.props.set_double("OfxImagePropPixelAspectRatio", 1.0);
+        image.props.set_str("OfxImageEffectPropPreMultiplication", "OfxImageUnPreMultiplied");
+        image.props.set_str("OfxImagePropField", "OfxImageFieldNone");
+        image.props.set_str("OfxImagePropUniqueIdentifier", &clip.name);
+        image.data = std::mem::take(&mut frame.data);
+
+        clip.props.set_str("OfxImageEffectPropPixelDepth", frame.depth.ofx_name());
+        clip.props.set_str("OfxImageClipPropUnmappedPixelDepth", frame.depth.ofx_name());
+        clip.props.set_str("OfxImageEffectPropComponents", frame.components());
+        clip.props.set_str("OfxImageClipPropUnmappedComponents", frame.components());
+        clip.image = Some(image);
+    }
+
+    pub fn set_source(&mut self, frame: Frame) {
+        self.source = frame.clone();
+        self.set_clip_frame("Source", frame);
+    }
+
+    pub fn param_mut(&mut self, name: &str) -> &mut Param {
+        self.effect.params.get_mut(name).unwrap_or_else(|| panic!("No such param: {name}"))
+    }
+    pub fn param(&self, name: &str) -> &Param {
+        self.effect.params.get(name).unwrap_or_else(|| panic!("No such param: {name}"))
+    }
+
+    /// Notifies the plugin about a user edit, like a host does after the value was changed in the UI
+    pub fn instance_changed(&mut self, name: &str) -> c_int {
+        let mut in_args = PropertySet::default();
+        in_args.set_str("OfxPropType", "OfxTypeParameter");
+        in_args.set_str("OfxPropName", name);
+        in_args.set_str("OfxPropChangeReason", "OfxChangeUserEdited");
+        in_args.set_double("OfxPropTime", 0.0);
+        in_args.set_doubles("OfxImageEffectPropRenderScale", &[1.0, 1.0]);
+        let handle = self.handle();
+        self.host.action("OfxActionBeginInstanceChanged", handle, None, None);
+        let status = self.host.action("OfxActionInstanceChanged", handle, Some(&mut in_args), None);
+        self.host.action("OfxActionEndInstanceChanged", handle, None, None);
+        status
+    }
+
+    pub fn set_string(&mut self, name: &str, v: &str) -> c_int {
+        self.param_mut(name).value = ParamValue::String(CString::new(v).unwrap());
+        self.instance_changed(name)
+    }
+    pub fn set_double(&mut self, name: &str, v: f64) -> c_int {
+        self.param_mut(name).value = ParamValue::Double(vec![v]);
+        self.instance_changed(name)
+    }
+    /// Adds a key, animating the param linearly between the keys
+    pub fn set_double_key(&mut self, name: &str, time: f64, v: f64) -> c_int {
+        self.param_mut(name).set_key(time, ParamValue::Double(vec![v]));
+        self.instance_changed(name)
+    }
+    pub fn set_bool(&mut self, name: &str, v: bool) -> c_int {
+        self.param_mut(name).value = ParamValue::Int(vec![v as c_int]);
+        self.instance_changed(name)
+    }
+    pub fn set_int(&mut self, name: &str, v: i32) -> c_int {
+        self.param_mut(name).value = ParamValue::Int(vec![v]);
+        self.instance_changed(name)
+    }
+    pub fn press(&mut self, name: &str) -> c_int {
+        self.instance_changed(name)
+    }
+
+    pub fn get_string(&self, name: &str) -> String {
+        match &self.param(name).value { ParamValue::String(s) => s.to_string_lossy().to_string(), v => panic!("{name} is not a string: {v:?}") }
+    }
+    pub fn get_double(&self, name: &str) -> f64 {
+        match &self.param(name).value { ParamValue::Double(v) => v[0], v => panic!("{name} is not a double: {v:?}") }
+    }
+    pub fn get_int(&self, name: &str) -> i32 {
+        match &self.param(name).value { ParamValue::Int(v) => v[0], v => panic!("{name} is not an integer: {v:?}") }
+    }
+    pub fn get_bool(&self, name: &str) -> bool {
+        match &self.param(name).value { ParamValue::Int(v) => v[0] != 0, v => panic!("{name} is not a boolean: {v:?}") }
+    }
+    pub fn label(&self, name: &str) -> String { self.param(name).props.get_str("OfxPropLabel").unwrap_or_default() }
+    pub fn hint(&self, name: &str) -> String { self.param(name).props.get_str("OfxParamPropHint").unwrap_or_default() }
+
+    /// Components and depth requested by the plugin, as `OfxImageClipPropComponents_Source`, `OfxImageClipPropComponents_Output` and `OfxImageClipPropDepth_Output`
+    pub fn clip_preferences(&mut self) -> PropertySet {
+        let mut out_args = PropertySet::default();
+        let handle = self.handle();
+        assert_eq!(self.host.action("OfxImageEffectActionGetClipPreferences", handle, None, Some(&mut out_args)), STAT_OK);
+        out_args
+    }
+
+    /// Renders a full frame into a new RGBA output buffer of the given size and depth
+    pub fn render_sized(&mut self, time: f64, width: usize, height: usize, depth: BitDepth) -> (c_int, Frame) {
+        self.render_into(time, Frame::new(width, height, depth))
+    }
+    pub fn render_into(&mut self, time: f64, output: Frame) -> (c_int, Frame) {
+        let size = (output.width, output.height);
+        self.render_window(time, output, [0, 0, size.0, size.1], size)
+    }
+    /// Renders `window` of a frame of `size` into `output`, which has the size of the window
+    fn render_window(&mut self, time: f64, output: Frame, window: [usize; 4], size: (usize, usize)) -> (c_int, Frame) {
+        let (width, height, depth, channels) = (output.width, output.height, output.depth, output.channels);
+        self.set_clip_tile("Output", output, window, size);
+
+        let mut in_args = PropertySet::default();
+        in_args.set_double("OfxPropTime", time);
+        in_args.set_str("OfxImageEffectPropFieldToRender", "OfxImageFieldNone");
+        in_args.set_ints("OfxImageEffectPropRenderWindow", &window.map(|x| x as c_int));
+        in_args.set_doubles("OfxImageEffectPropRenderScale", &[1.0, 1.0]);
+        in_args.set_int("OfxImageEffectPropSequentialRenderStatus", 0);
+        in_args.set_int("OfxImageEffectPropInteractiveRenderStatus", self.interactive as c_int);
+        in_args.set_int("OfxImageEffectPropRenderQualityDraft", self.draft as c_int);
+        let handle = self.handle();
+        let status = self.host.action("OfxImageEffectActionRender", handle, Some(&mut in_args), None);
+
+        let image = self.effect.clip_mut("Output").unwrap().image.take().unwrap();
+        let mut frame = Frame { width, height, depth, channels, data: image.data };
+        if self.top_down { frame.flip_rows(); }
+        (status, frame)
+    }
+    /// Name of the clip which is passed through at `time`, if the plugin doesn't change the frame
+    pub fn is_identity(&mut self, time: f64) -> Option<String> {
+        let mut in_args = PropertySet::default();
+        in_args.set_double("OfxPropTime", time);
+        in_args.set_str("OfxImageEffectPropFieldToRender", "OfxImageFieldNone");
+        in_args.set_ints("OfxImageEffectPropRenderWindow", &[0, 0, self.source.width as c_int, self.source.height as c_int]);
+        in_args.set_doubles("OfxImageEffectPropRenderScale", &[1.0, 1.0]);
+        let mut out_args = PropertySet::default();
+        out_args.set_str("OfxPropName", "");
+        out_args.set_double("OfxPropTime", time);
+        let handle = self.handle();
+        match self.host.action("OfxImageEffectActionIsIdentity", handle, Some(&mut in_args), Some(&mut out_args)) {
+            STAT_OK => out_args.get_str("OfxPropName"),
+            STAT_REPLY_DEFAULT => None,
+            status => panic!("IsIdentity failed: {status}")
+        }
+    }
+
+    /// Region of the source requested by the plugin to render `roi` of the output, as `[x1, y1, x2, y2]`
+    pub fn regions_of_interest(&mut self, time: f64, roi: [f64; 4]) -> [f64; 4] {
+        let mut in_args = PropertySet::default();
+        in_args.set_double("OfxPropTime", time);
+        in_args.set_doubles("OfxImageEffectPropRenderScale", &[1.0, 1.0]);
+        in_args.set_doubles("OfxImageEffectPropRegionOfInterest", &roi);
+        let mut out_args = PropertySet::default();
+        let handle = self.handle();
+        assert_eq!(self.host.action("OfxImageEffectActionGetRegionsOfInterest", handle, Some(&mut in_args), Some(&mut out_args)), STAT_OK);
+        let source_roi = out_args.get_doubles("OfxImageClipPropRoI_Source");
+        [source_roi[0], source_roi[1], source_roi[2], source_roi[3]]
+    }
+
+    /// Renders a tile of the output like a host with tiles support, with only the region of interest of the source
+    pub fn render_tile(&mut self, time: f64, window: [usize; 4]) -> (c_int, Frame) {
+        let source = self.source.clone();
+        let size = (source.width, source.height);
+        let roi = self.regions_of_interest(time, window.map(|x| x as f64));
+        let roi = [
+            (roi[0].floor().max(0.0) as usize).min(size.0), (roi[1].floor().max(0.0) as usize).min(size.1),
+            (roi[2].ceil().max(0.0) as usize).min(size.0), (roi[3].ceil().max(0.0) as usize).min(size.1)
+        ];
+        self.set_clip_tile("Source", source.crop(roi), roi, size);
+
+        let output = Frame::with_channels(window[2] - window[0], window[3] - window[1], source.depth, source.channels);
+        let ret = self.render_window(time, output, window, size);
+        self.set_clip_frame("Source", source);
+        ret
+    }
+
+    /// Renders a frame like the source clip, with the output components from the clip preferences
+    pub fn render(&mut self, time: f64) -> (c_int, Frame) {
+        let src = &self.effect.clip("Source").unwrap().image.as_ref().unwrap().props;
+        let bounds = src.get_doubles("OfxImagePropRegionOfDefinition");
+        let depth = match src.get_str("OfxImageEffectPropPixelDepth").as_deref() {
+            Some("OfxBitDepthByte") => BitDepth::Byte,
+            Some("OfxBitDepthShort") => BitDepth::Short,
+            Some("OfxBitDepthHalf") => BitDepth::Half,
+            _ => BitDepth::Float
+        };
+        let channels = match self.clip_preferences().get_str("OfxImageClipPropComponents_Output").as_deref() {
+            Some("OfxImageComponentAlpha") => 1,
+            Some("OfxImageComponentRGB") => 3,
+            _ => 4
+        };
+        self.render_into(time, Frame::with_channels(bounds[2] as usize, bounds[3] as usize, depth, channels))
+    }
+}
+impl Drop for Instance<'_> {
+    fn drop(&mut self) {
+        let handle = self.handle();
+        self.host.action("OfxActionDestroyInstance", handle, None, None);
+    }
+}
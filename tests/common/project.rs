@@ -0,0 +1,155 @@
+// Synthetic `.gyroflow` projects with known gyro motion and a known lens profile.
+// Everything is derived from the parameters, so the same project is produced on every run and every machine.
+
+use std::path::PathBuf;
+
+#[derive(Clone, Debug)]
+pub struct SyntheticProject {
+    pub name: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub fps: f64,
+    pub num_frames: usize,
+    /// Peak angular velocity of the sinusoidal shake per axis, in deg/s
+    pub shake_amplitude: [f64; 3],
+    /// Shake frequency in Hz
+    pub shake_frequency: f64,
+    /// Constant rotation added on top of the shake, in deg/s
+    pub constant_rotation: [f64; 3],
+    /// Fisheye focal length in pixels and distortion coefficients of the lens profile
+    pub focal_length: f64,
+    pub distortion: [f64; 4],
+    pub smoothness: f64,
+    pub fov: f64,
+}
+impl Default for SyntheticProject {
+    fn default() -> Self {
+        Self {
+            name: "default",
+            width: 320,
+            height: 180,
+            fps: 30.0,
+            num_frames: 60,
+            shake_amplitude: [20.0, 15.0, 10.0],
+            shake_frequency: 3.0,
+            constant_rotation: [0.0, 5.0, 0.0],
+            focal_length: 160.0,
+            distortion: [0.05, -0.01, 0.0, 0.0],
+            smoothness: 0.5,
+            fov: 1.0,
+        }
+    }
+}
+
+impl SyntheticProject {
+    const GYRO_RATE: f64 = 200.0;
+
+    pub fn gyro_at(&self, t_s: f64) -> [f64; 3] {
+        let phase = 2.0 * std::f64::consts::PI * self.shake_frequency * t_s;
+        [
+            self.constant_rotation[0] + self.shake_amplitude[0] * phase.sin(),
+            self.constant_rotation[1] + self.shake_amplitude[1] * (phase * 1.3).cos(),
+            self.constant_rotation[2] + self.shake_amplitude[2] * (phase * 0.7).sin(),
+        ]
+    }
+
+    pub fn duration_ms(&self) -> f64 { self.num_frames as f64 / self.fps * 1000.0 }
+
+    pub fn lens_profile(&self) -> String {
+        let (w, h) = (self.width, self.height);
+        let f = self.focal_length;
+        let k = self.distortion;
+        format!(r#"{{
+            "name": "Synthetic {w}x{h}",
+            "camera_brand": "Gyroflow",
+            "camera_model": "Synthetic",
+            "lens_model": "Test lens",
+            "camera_setting": "",
+            "calibrated_by": "gyroflow-ofx tests",
+            "calib_dimension": {{ "w": {w}, "h": {h} }},
+            "orig_dimension": {{ "w": {w}, "h": {h} }},
+            "output_dimension": {{ "w": {w}, "h": {h} }},
+            "frame_readout_time": 0.0,
+            "input_horizontal_stretch": 1.0,
+            "input_vertical_stretch": 1.0,
+            "num_images": 0,
+            "fps": {fps},
+            "official": false,
+            "asymmetrical": false,
+            "fisheye_params": {{
+                "RMS_error": 0.0,
+                "camera_matrix": [ [ {f}, 0.0, {cx} ], [ 0.0, {f}, {cy} ], [ 0.0, 0.0, 1.0 ] ],
+                "distortion_coeffs": [ {k0}, {k1}, {k2}, {k3} ],
+                "radial_distortion_limit": null
+            }},
+            "identifier": "synthetic_{w}x{h}",
+            "calibrator_version": "",
+            "date": "",
+            "compatible_settings": []
+        }}"#, fps = self.fps, cx = w as f64 / 2.0, cy = h as f64 / 2.0, k0 = k[0], k1 = k[1], k2 = k[2], k3 = k[3])
+    }
+
+    pub fn to_json(&self) -> String {
+        let samples = (self.duration_ms() / 1000.0 * Self::GYRO_RATE).ceil() as usize + 1;
+        let raw_imu = (0..samples).map(|i| {
+            let t = i as f64 / Self::GYRO_RATE;
+            let g = self.gyro_at(t);
+            format!(r#"{{ "timestamp_ms": {:.3}, "gyro": [ {:.6}, {:.6}, {:.6} ], "accl": [ 0.0, 9.81, 0.0 ] }}"#, t * 1000.0, g[0], g[1], g[2])
+        }).collect::<Vec<_>>().join(",\n");
+
+        format!(r#"{{
+            "title": "Gyroflow data file",
+            "version": 3,
+            "app_version": "1.6.0",
+            "videofile": "{name}.mp4",
+            "calibration_data": {lens},
+            "video_info": {{
+                "width": {w},
+                "height": {h},
+                "rotation": 0,
+                "num_frames": {frames},
+                "fps": {fps},
+                "duration_ms": {duration},
+                "fps_scale": null,
+                "vfr_fps": {fps},
+                "vfr_duration_ms": {duration}
+            }},
+            "stabilization": {{
+                "fov": {fov},
+                "method": "Default",
+                "smoothing_params": [ {{ "name": "smoothness", "value": {smoothness} }} ],
+                "frame_readout_time": 0.0,
+                "adaptive_zoom_window": 0.0,
+                "lens_correction_amount": 1.0,
+                "horizon_lock_amount": 0.0,
+                "horizon_lock_roll": 0.0,
+                "use_gravity_vectors": false,
+                "video_speed": 1.0
+            }},
+            "gyro_source": {{
+                "filepath": "",
+                "lpf": 0.0,
+                "rotation": [ 0.0, 0.0, 0.0 ],
+                "imu_orientation": "XYZ",
+                "integration_method": 1,
+                "raw_imu": [ {raw_imu} ]
+            }},
+            "offsets": {{ "0": 0.0 }},
+            "keyframes": {{ }},
+            "trim_start": 0.0,
+            "trim_end": 1.0,
+            "output": {{ "output_width": {w}, "output_height": {h} }}
+        }}"#,
+            name = self.name, lens = self.lens_profile(), w = self.width, h = self.height, frames = self.num_frames,
+            fps = self.fps, duration = self.duration_ms(), fov = self.fov, smoothness = self.smoothness)
+    }
+
+    /// Writes the project to a unique file in the temp directory and returns its path
+    pub fn write(&self) -> PathBuf {
+        let dir = std::env::temp_dir().join("gyroflow-ofx-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}-{}.gyroflow", self.name, std::process::id()));
+        std::fs::write(&path, self.to_json()).unwrap();
+        path
+    }
+}
@@ -0,0 +1,700 @@
+// Minimal C ABI of the OpenFX host side, together with the suite implementations the plugin fetches.
+// Only what ofx-rs and the plugin actually use is implemented, everything else reports kOfxStatErrUnsupported.
+// The host is single-threaded: `multiThread` runs the callbacks serially and mutexes are no-ops.
+// The background jobs and the project watcher don't call the host, they only set a flag and `RenderRevision` is changed by the next `InstanceChanged`.
+
+#![allow(non_snake_case, clippy::missing_safety_doc)]
+
+use std::collections::HashMap;
+use std::ffi::{ c_char, c_int, c_uint, c_void, CStr, CString };
+use std::sync::atomic::{ AtomicUsize, Ordering::SeqCst };
+
+pub type OfxStatus = c_int;
+pub type Handle = *mut c_void;
+
+pub const STAT_OK:            OfxStatus = 0;
+pub const STAT_FAILED:        OfxStatus = 1;
+pub const STAT_ERR_UNKNOWN:   OfxStatus = 3;
+pub const STAT_ERR_UNSUPPORTED: OfxStatus = 5;
+pub const STAT_ERR_BAD_HANDLE: OfxStatus = 9;
+pub const STAT_ERR_BAD_INDEX: OfxStatus = 10;
+pub const STAT_REPLY_DEFAULT: OfxStatus = 14;
+
+/// Number of `paramGetValueAtTime` calls made by the plugin, to check how much it queries the host
+pub static VALUE_AT_TIME_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OfxRectD { pub x1: f64, pub y1: f64, pub x2: f64, pub y2: f64 }
+
+#[repr(C)]
+pub struct OfxHost {
+    pub host: Handle,
+    pub fetchSuite: unsafe extern "C" fn(host: Handle, suite_name: *const c_char, suite_version: c_int) -> *const c_void,
+}
+
+pub type OfxPluginEntryPoint = unsafe extern "C" fn(action: *const c_char, handle: *const c_void, in_args: Handle, out_args: Handle) -> OfxStatus;
+
+#[repr(C)]
+pub struct OfxPlugin {
+    pub pluginApi: *const c_char,
+    pub apiVersion: c_int,
+    pub pluginIdentifier: *const c_char,
+    pub pluginVersionMajor: c_uint,
+    pub pluginVersionMinor: c_uint,
+    pub setHost: Option<unsafe extern "C" fn(host: *mut OfxHost)>,
+    pub mainEntry: Option<OfxPluginEntryPoint>,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Property sets
+// ---------------------------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Pointer(*mut c_void),
+    String(CString),
+    Double(f64),
+    Int(c_int),
+}
+impl Value {
+    pub fn str(v: &str) -> Self { Value::String(CString::new(v).unwrap()) }
+    fn as_int(&self) -> Option<c_int> {
+        match self { Value::Int(v) => Some(*v), Value::Double(v) => Some(*v as c_int), _ => None }
+    }
+    fn as_double(&self) -> Option<f64> {
+        match self { Value::Double(v) => Some(*v), Value::Int(v) => Some(*v as f64), _ => None }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PropertySet {
+    values: HashMap<String, Vec<Value>>
+}
+impl PropertySet {
+    pub fn set(&mut self, name: &str, values: Vec<Value>) { self.values.insert(name.to_owned(), values); }
+    pub fn set_str    (&mut self, name: &str, v: &str)   { self.set(name, vec![Value::str(v)]); }
+    pub fn set_int    (&mut self, name: &str, v: c_int)  { self.set(name, vec![Value::Int(v)]); }
+    pub fn set_double (&mut self, name: &str, v: f64)    { self.set(name, vec![Value::Double(v)]); }
+    pub fn set_ptr    (&mut self, name: &str, v: Handle) { self.set(name, vec![Value::Pointer(v)]); }
+    pub fn set_ints   (&mut self, name: &str, v: &[c_int]) { self.set(name, v.iter().map(|x| Value::Int(*x)).collect()); }
+    pub fn set_doubles(&mut self, name: &str, v: &[f64])   { self.set(name, v.iter().map(|x| Value::Double(*x)).collect()); }
+    pub fn set_strs   (&mut self, name: &str, v: &[&str])  { self.set(name, v.iter().map(|x| Value::str(x)).collect()); }
+
+    pub fn contains(&self, name: &str) -> bool { self.values.contains_key(name) }
+    pub fn get_str(&self, name: &str) -> Option<String> {
+        match self.values.get(name)?.first()? { Value::String(s) => Some(s.to_string_lossy().to_string()), _ => None }
+    }
+    pub fn get_strs(&self, name: &str) -> Vec<String> {
+        self.values.get(name).map(|v| v.iter().filter_map(|x| match x { Value::String(s) => Some(s.to_string_lossy().to_string()), _ => None }).collect()).unwrap_or_default()
+    }
+    pub fn get_int(&self, name: &str) -> Option<c_int> { self.values.get(name)?.first()?.as_int() }
+    pub fn get_double(&self, name: &str) -> Option<f64> { self.values.get(name)?.first()?.as_double() }
+    pub fn get_doubles(&self, name: &str) -> Vec<f64> {
+        self.values.get(name).map(|v| v.iter().filter_map(Value::as_double).collect()).unwrap_or_default()
+    }
+
+    fn set_index(&mut self, name: &str, index: c_int, v: Value) -> OfxStatus {
+        if index < 0 { return STAT_ERR_BAD_INDEX; }
+        let index = index as usize;
+        let entry = self.values.entry(name.to_owned()).or_default();
+        if entry.len() <= index {
+            entry.resize(index + 1, v.clone());
+        }
+        entry[index] = v;
+        STAT_OK
+    }
+    fn get_index(&self, name: &str, index: c_int) -> Result<&Value, OfxStatus> {
+        let values = self.values.get(name).ok_or(STAT_ERR_UNKNOWN)?;
+        values.get(index.max(0) as usize).ok_or(STAT_ERR_BAD_INDEX)
+    }
+}
+
+unsafe fn props<'a>(h: Handle) -> Option<&'a mut PropertySet> { (h as *mut PropertySet).as_mut() }
+unsafe fn name<'a>(s: *const c_char) -> &'a str { if s.is_null() { "" } else { CStr::from_ptr(s).to_str().unwrap_or_default() } }
+
+macro_rules! prop_setter {
+    ($fn_name:ident, $fn_name_n:ident, $typ:ty, $conv:expr) => {
+        unsafe extern "C" fn $fn_name(h: Handle, property: *const c_char, index: c_int, value: $typ) -> OfxStatus {
+            let Some(p) = props(h) else { return STAT_ERR_BAD_HANDLE; };
+            p.set_index(name(property), index, $conv(value))
+        }
+        unsafe extern "C" fn $fn_name_n(h: Handle, property: *const c_char, count: c_int, value: *const $typ) -> OfxStatus {
+            let Some(p) = props(h) else { return STAT_ERR_BAD_HANDLE; };
+            let values = (0..count.max(0) as usize).map(|i| $conv(*value.add(i))).collect();
+            p.set(name(property), values);
+            STAT_OK
+        }
+    };
+}
+prop_setter!(prop_set_pointer, prop_set_pointer_n, *mut c_void,   |v| Value::Pointer(v));
+prop_setter!(prop_set_string,  prop_set_string_n,  *const c_char, |v| Value::String(CStr::from_ptr(v).to_owned()));
+prop_setter!(prop_set_double,  prop_set_double_n,  f64,           |v| Value::Double(v));
+prop_setter!(prop_set_int,     prop_set_int_n,     c_int,         |v| Value::Int(v));
+
+macro_rules! prop_getter {
+    ($fn_name:ident, $fn_name_n:ident, $typ:ty, $conv:expr) => {
+        unsafe extern "C" fn $fn_name(h: Handle, property: *const c_char, index: c_int, value: *mut $typ) -> OfxStatus {
+            let Some(p) = props(h) else { return STAT_ERR_BAD_HANDLE; };
+            match p.get_index(name(property), index) {
+                Ok(v) => match $conv(v) { Some(v) => { *value = v; STAT_OK }, None => STAT_ERR_UNKNOWN },
+                Err(e) => e
+            }
+        }
+        unsafe extern "C" fn $fn_name_n(h: Handle, property: *const c_char, count: c_int, value: *mut $typ) -> OfxStatus {
+            for i in 0..count {
+                let status = $fn_name(h, property, i, value.add(i as usize));
+                if status != STAT_OK { return status; }
+            }
+            STAT_OK
+        }
+    };
+}
+prop_getter!(prop_get_pointer, prop_get_pointer_n, *mut c_void, |v: &Value| match v { Value::Pointer(p) => Some(*p), _ => None });
+prop_getter!(prop_get_string,  prop_get_string_n,  *mut c_char, |v: &Value| match v { Value::String(s) => Some(s.as_ptr() as *mut c_char), _ => None });
+prop_getter!(prop_get_double,  prop_get_double_n,  f64,         |v: &Value| v.as_double());
+prop_getter!(prop_get_int,     prop_get_int_n,     c_int,       |v: &Value| v.as_int());
+
+unsafe extern "C" fn prop_reset(h: Handle, property: *const c_char) -> OfxStatus {
+    let Some(p) = props(h) else { return STAT_ERR_BAD_HANDLE; };
+    p.values.remove(name(property));
+    STAT_OK
+}
+unsafe extern "C" fn prop_get_dimension(h: Handle, property: *const c_char, count: *mut c_int) -> OfxStatus {
+    let Some(p) = props(h) else { return STAT_ERR_BAD_HANDLE; };
+    match p.values.get(name(property)) {
+        Some(v) => { *count = v.len() as c_int; STAT_OK },
+        None => STAT_ERR_UNKNOWN
+    }
+}
+
+#[repr(C)]
+pub struct OfxPropertySuiteV1 {
+    propSetPointer:   unsafe extern "C" fn(Handle, *const c_char, c_int, *mut c_void) -> OfxStatus,
+    propSetString:    unsafe extern "C" fn(Handle, *const c_char, c_int, *const c_char) -> OfxStatus,
+    propSetDouble:    unsafe extern "C" fn(Handle, *const c_char, c_int, f64) -> OfxStatus,
+    propSetInt:       unsafe extern "C" fn(Handle, *const c_char, c_int, c_int) -> OfxStatus,
+    propSetPointerN:  unsafe extern "C" fn(Handle, *const c_char, c_int, *const *mut c_void) -> OfxStatus,
+    propSetStringN:   unsafe extern "C" fn(Handle, *const c_char, c_int, *const *const c_char) -> OfxStatus,
+    propSetDoubleN:   unsafe extern "C" fn(Handle, *const c_char, c_int, *const f64) -> OfxStatus,
+    propSetIntN:      unsafe extern "C" fn(Handle, *const c_char, c_int, *const c_int) -> OfxStatus,
+    propGetPointer:   unsafe extern "C" fn(Handle, *const c_char, c_int, *mut *mut c_void) -> OfxStatus,
+    propGetString:    unsafe extern "C" fn(Handle, *const c_char, c_int, *mut *mut c_char) -> OfxStatus,
+    propGetDouble:    unsafe extern "C" fn(Handle, *const c_char, c_int, *mut f64) -> OfxStatus,
+    propGetInt:       unsafe extern "C" fn(Handle, *const c_char, c_int, *mut c_int) -> OfxStatus,
+    propGetPointerN:  unsafe extern "C" fn(Handle, *const c_char, c_int, *mut *mut c_void) -> OfxStatus,
+    propGetStringN:   unsafe extern "C" fn(Handle, *const c_char, c_int, *mut *mut c_char) -> OfxStatus,
+    propGetDoubleN:   unsafe extern "C" fn(Handle, *const c_char, c_int, *mut f64) -> OfxStatus,
+    propGetIntN:      unsafe extern "C" fn(Handle, *const c_char, c_int, *mut c_int) -> OfxStatus,
+    propReset:        unsafe extern "C" fn(Handle, *const c_char) -> OfxStatus,
+    propGetDimension: unsafe extern "C" fn(Handle, *const c_char, *mut c_int) -> OfxStatus,
+}
+pub static PROPERTY_SUITE: OfxPropertySuiteV1 = OfxPropertySuiteV1 {
+    propSetPointer:   prop_set_pointer,
+    propSetString:    prop_set_string,
+    propSetDouble:    prop_set_double,
+    propSetInt:       prop_set_int,
+    propSetPointerN:  prop_set_pointer_n,
+    propSetStringN:   prop_set_string_n,
+    propSetDoubleN:   prop_set_double_n,
+    propSetIntN:      prop_set_int_n,
+    propGetPointer:   prop_get_pointer,
+    propGetString:    prop_get_string,
+    propGetDouble:    prop_get_double,
+    propGetInt:       prop_get_int,
+    propGetPointerN:  prop_get_pointer_n,
+    propGetStringN:   prop_get_string_n,
+    propGetDoubleN:   prop_get_double_n,
+    propGetIntN:      prop_get_int_n,
+    propReset:        prop_reset,
+    propGetDimension: prop_get_dimension,
+};
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Parameters
+// ---------------------------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamValue {
+    None,
+    Int(Vec<c_int>),
+    Double(Vec<f64>),
+    String(CString),
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: String,
+    pub kind: String,
+    pub props: PropertySet,
+    pub value: ParamValue,
+    pub keys: Vec<(f64, ParamValue)>,
+    /// Keys are interpolated with ease in and out like the default curves of most hosts, instead of linearly
+    pub eased: bool,
+}
+impl Param {
+    fn new(kind: &str, name: &str) -> Self {
+        let mut props = PropertySet::default();
+        props.set_str("OfxPropType", "OfxTypeParameter");
+        props.set_str("OfxPropName", name);
+        props.set_str("OfxParamPropType", kind);
+        props.set_str("OfxPropLabel", name);
+        Self { name: name.to_owned(), kind: kind.to_owned(), props, value: ParamValue::None, keys: Vec::new(), eased: false }
+    }
+    fn dimension(&self) -> usize {
+        match self.kind.as_str() {
+            "OfxParamTypeDouble2D" | "OfxParamTypeInteger2D" => 2,
+            "OfxParamTypeDouble3D" | "OfxParamTypeInteger3D" | "OfxParamTypeRGB" => 3,
+            "OfxParamTypeRGBA" => 4,
+            _ => 1
+        }
+    }
+    /// Builds the initial instance value from the descriptor's `OfxParamPropDefault`
+    pub fn reset_to_default(&mut self) {
+        let dim = self.dimension();
+        self.value = match self.kind.as_str() {
+            "OfxParamTypeDouble" | "OfxParamTypeDouble2D" | "OfxParamTypeDouble3D" | "OfxParamTypeRGB" | "OfxParamTypeRGBA" => {
+                let mut v = self.props.get_doubles("OfxParamPropDefault");
+                v.resize(dim, 0.0);
+                ParamValue::Double(v)
+            },
+            "OfxParamTypeInteger" | "OfxParamTypeInteger2D" | "OfxParamTypeInteger3D" | "OfxParamTypeBoolean" | "OfxParamTypeChoice" => {
+                let mut v = self.props.get_doubles("OfxParamPropDefault").into_iter().map(|x| x as c_int).collect::<Vec<_>>();
+                v.resize(dim, 0);
+                ParamValue::Int(v)
+            },
+            "OfxParamTypeString" | "OfxParamTypeStrChoice" | "OfxParamTypeCustom" => {
+                ParamValue::String(CString::new(self.props.get_str("OfxParamPropDefault").unwrap_or_default()).unwrap())
+            },
+            _ => ParamValue::None
+        };
+        self.keys.clear();
+    }
+    pub fn value_at_time(&self, time: f64) -> ParamValue {
+        if self.keys.is_empty() { return self.value.clone(); }
+        let next = self.keys.iter().position(|(t, _)| *t >= time);
+        match next {
+            None => self.keys.last().unwrap().1.clone(),
+            Some(0) => self.keys[0].1.clone(),
+            Some(i) => {
+                let (t0, v0) = &self.keys[i - 1];
+                let (t1, v1) = &self.keys[i];
+                match (v0, v1) {
+                    (ParamValue::Double(a), ParamValue::Double(b)) => {
+                        let f = (time - t0) / (t1 - t0);
+                        let f = if self.eased { f * f * (3.0 - 2.0 * f) } else { f };
+                        ParamValue::Double(a.iter().zip(b).map(|(a, b)| a + (b - a) * f).collect())
+                    },
+                    _ => v0.clone()
+                }
+            }
+        }
+    }
+    pub fn set_key(&mut self, time: f64, value: ParamValue) {
+        match self.keys.iter().position(|(t, _)| *t >= time) {
+            Some(i) if self.keys[i].0 == time => self.keys[i].1 = value,
+            Some(i) => self.keys.insert(i, (time, value)),
+            None => self.keys.push((time, value)),
+        }
+    }
+
+    unsafe fn write_value(&self, value: &ParamValue, args: &mut std::ffi::VaListImpl) {
+        match value {
+            ParamValue::Double(v) => for x in v { *args.arg::<*mut f64>() = *x; },
+            ParamValue::Int(v)    => for x in v { *args.arg::<*mut c_int>() = *x; },
+            ParamValue::String(s) => { *args.arg::<*mut *const c_char>() = s.as_ptr(); },
+            ParamValue::None => { }
+        }
+    }
+    unsafe fn read_value(&self, args: &mut std::ffi::VaListImpl) -> ParamValue {
+        match &self.value {
+            ParamValue::Double(v) => ParamValue::Double((0..v.len()).map(|_| args.arg::<f64>()).collect()),
+            ParamValue::Int(v)    => ParamValue::Int((0..v.len()).map(|_| args.arg::<c_int>()).collect()),
+            ParamValue::String(_) => ParamValue::String(CStr::from_ptr(args.arg::<*const c_char>()).to_owned()),
+            ParamValue::None => ParamValue::None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ParamSet {
+    pub props: PropertySet,
+    pub params: Vec<Box<Param>>,
+}
+impl ParamSet {
+    pub fn get(&self, name: &str) -> Option<&Param> { self.params.iter().find(|x| x.name == name).map(|x| &**x) }
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Param> { self.params.iter_mut().find(|x| x.name == name).map(|x| &mut **x) }
+}
+
+unsafe fn param<'a>(h: Handle) -> Option<&'a mut Param> { (h as *mut Param).as_mut() }
+
+unsafe extern "C" fn param_define(set: Handle, kind: *const c_char, pname: *const c_char, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(set) = (set as *mut ParamSet).as_mut() else { return STAT_ERR_BAD_HANDLE; };
+    let mut p = Box::new(Param::new(name(kind), name(pname)));
+    if !prop_handle.is_null() {
+        *prop_handle = &mut p.props as *mut _ as Handle;
+    }
+    set.params.push(p);
+    STAT_OK
+}
+unsafe extern "C" fn param_get_handle(set: Handle, pname: *const c_char, handle: *mut Handle, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(set) = (set as *mut ParamSet).as_mut() else { return STAT_ERR_BAD_HANDLE; };
+    let Some(p) = set.get_mut(name(pname)) else { return STAT_ERR_UNKNOWN; };
+    *handle = p as *mut _ as Handle;
+    if !prop_handle.is_null() {
+        *prop_handle = &mut p.props as *mut _ as Handle;
+    }
+    STAT_OK
+}
+unsafe extern "C" fn param_set_get_property_set(set: Handle, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(set) = (set as *mut ParamSet).as_mut() else { return STAT_ERR_BAD_HANDLE; };
+    *prop_handle = &mut set.props as *mut _ as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn param_get_property_set(h: Handle, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    *prop_handle = &mut p.props as *mut _ as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn param_get_value(h: Handle, mut args: ...) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    // Like most hosts, the "current" value of an animated param is its value at the current time, which is always 0 here
+    let v = p.value_at_time(0.0);
+    p.write_value(&v, &mut args);
+    STAT_OK
+}
+unsafe extern "C" fn param_get_value_at_time(h: Handle, time: f64, mut args: ...) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    VALUE_AT_TIME_CALLS.fetch_add(1, SeqCst);
+    let v = p.value_at_time(time);
+    p.write_value(&v, &mut args);
+    STAT_OK
+}
+unsafe extern "C" fn param_get_derivative(_h: Handle, _time: f64, _args: ...) -> OfxStatus { STAT_ERR_UNSUPPORTED }
+unsafe extern "C" fn param_get_integral(_h: Handle, _time1: f64, _time2: f64, _args: ...) -> OfxStatus { STAT_ERR_UNSUPPORTED }
+unsafe extern "C" fn param_set_value(h: Handle, mut args: ...) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    p.value = p.read_value(&mut args);
+    STAT_OK
+}
+unsafe extern "C" fn param_set_value_at_time(h: Handle, time: f64, mut args: ...) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    let v = p.read_value(&mut args);
+    p.set_key(time, v);
+    STAT_OK
+}
+unsafe extern "C" fn param_get_num_keys(h: Handle, num: *mut c_uint) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    *num = p.keys.len() as c_uint;
+    STAT_OK
+}
+unsafe extern "C" fn param_get_key_time(h: Handle, nth: c_uint, time: *mut f64) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    match p.keys.get(nth as usize) {
+        Some((t, _)) => { *time = *t; STAT_OK },
+        None => STAT_ERR_BAD_INDEX
+    }
+}
+unsafe extern "C" fn param_get_key_index(h: Handle, time: f64, direction: c_int, index: *mut c_int) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    let found = match direction {
+        0          => p.keys.iter().position(|(t, _)| *t == time),
+        d if d < 0 => p.keys.iter().rposition(|(t, _)| *t < time),
+        _          => p.keys.iter().position(|(t, _)| *t > time),
+    };
+    match found {
+        Some(i) => { *index = i as c_int; STAT_OK },
+        None => STAT_FAILED
+    }
+}
+unsafe extern "C" fn param_delete_key(h: Handle, time: f64) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    match p.keys.iter().position(|(t, _)| *t == time) {
+        Some(i) => { p.keys.remove(i); STAT_OK },
+        None => STAT_ERR_BAD_INDEX
+    }
+}
+unsafe extern "C" fn param_delete_all_keys(h: Handle) -> OfxStatus {
+    let Some(p) = param(h) else { return STAT_ERR_BAD_HANDLE; };
+    p.keys.clear();
+    STAT_OK
+}
+unsafe extern "C" fn param_copy(_to: Handle, _from: Handle, _offset: f64, _range: *const c_void) -> OfxStatus { STAT_ERR_UNSUPPORTED }
+unsafe extern "C" fn param_edit_begin(_set: Handle, _name: *const c_char) -> OfxStatus { STAT_OK }
+unsafe extern "C" fn param_edit_end(_set: Handle) -> OfxStatus { STAT_OK }
+
+#[repr(C)]
+pub struct OfxParameterSuiteV1 {
+    paramDefine:            unsafe extern "C" fn(Handle, *const c_char, *const c_char, *mut Handle) -> OfxStatus,
+    paramGetHandle:         unsafe extern "C" fn(Handle, *const c_char, *mut Handle, *mut Handle) -> OfxStatus,
+    paramSetGetPropertySet: unsafe extern "C" fn(Handle, *mut Handle) -> OfxStatus,
+    paramGetPropertySet:    unsafe extern "C" fn(Handle, *mut Handle) -> OfxStatus,
+    paramGetValue:          unsafe extern "C" fn(Handle, ...) -> OfxStatus,
+    paramGetValueAtTime:    unsafe extern "C" fn(Handle, f64, ...) -> OfxStatus,
+    paramGetDerivative:     unsafe extern "C" fn(Handle, f64, ...) -> OfxStatus,
+    paramGetIntegral:       unsafe extern "C" fn(Handle, f64, f64, ...) -> OfxStatus,
+    paramSetValue:          unsafe extern "C" fn(Handle, ...) -> OfxStatus,
+    paramSetValueAtTime:    unsafe extern "C" fn(Handle, f64, ...) -> OfxStatus,
+    paramGetNumKeys:        unsafe extern "C" fn(Handle, *mut c_uint) -> OfxStatus,
+    paramGetKeyTime:        unsafe extern "C" fn(Handle, c_uint, *mut f64) -> OfxStatus,
+    paramGetKeyIndex:       unsafe extern "C" fn(Handle, f64, c_int, *mut c_int) -> OfxStatus,
+    paramDeleteKey:         unsafe extern "C" fn(Handle, f64) -> OfxStatus,
+    paramDeleteAllKeys:     unsafe extern "C" fn(Handle) -> OfxStatus,
+    paramCopy:              unsafe extern "C" fn(Handle, Handle, f64, *const c_void) -> OfxStatus,
+    paramEditBegin:         unsafe extern "C" fn(Handle, *const c_char) -> OfxStatus,
+    paramEditEnd:           unsafe extern "C" fn(Handle) -> OfxStatus,
+}
+pub static PARAMETER_SUITE: OfxParameterSuiteV1 = OfxParameterSuiteV1 {
+    paramDefine:            param_define,
+    paramGetHandle:         param_get_handle,
+    paramSetGetPropertySet: param_set_get_property_set,
+    paramGetPropertySet:    param_get_property_set,
+    paramGetValue:          param_get_value,
+    paramGetValueAtTime:    param_get_value_at_time,
+    paramGetDerivative:     param_get_derivative,
+    paramGetIntegral:       param_get_integral,
+    paramSetValue:          param_set_value,
+    paramSetValueAtTime:    param_set_value_at_time,
+    paramGetNumKeys:        param_get_num_keys,
+    paramGetKeyTime:        param_get_key_time,
+    paramGetKeyIndex:       param_get_key_index,
+    paramDeleteKey:         param_delete_key,
+    paramDeleteAllKeys:     param_delete_all_keys,
+    paramCopy:              param_copy,
+    paramEditBegin:         param_edit_begin,
+    paramEditEnd:           param_edit_end,
+};
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Image effect, clips and images
+// ---------------------------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default)]
+pub struct Image {
+    pub props: PropertySet,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Clip {
+    pub name: String,
+    pub props: PropertySet,
+    pub image: Option<Box<Image>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Effect {
+    pub props: PropertySet,
+    pub params: Box<ParamSet>,
+    pub clips: Vec<Box<Clip>>,
+    pub abort: bool,
+}
+impl Effect {
+    pub fn clip(&self, name: &str) -> Option<&Clip> { self.clips.iter().find(|x| x.name == name).map(|x| &**x) }
+    pub fn clip_mut(&mut self, name: &str) -> Option<&mut Clip> { self.clips.iter_mut().find(|x| x.name == name).map(|x| &mut **x) }
+}
+
+unsafe fn effect<'a>(h: Handle) -> Option<&'a mut Effect> { (h as *mut Effect).as_mut() }
+unsafe fn clip<'a>(h: Handle) -> Option<&'a mut Clip> { (h as *mut Clip).as_mut() }
+
+unsafe extern "C" fn get_property_set(h: Handle, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(e) = effect(h) else { return STAT_ERR_BAD_HANDLE; };
+    *prop_handle = &mut e.props as *mut _ as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn get_param_set(h: Handle, set: *mut Handle) -> OfxStatus {
+    let Some(e) = effect(h) else { return STAT_ERR_BAD_HANDLE; };
+    *set = &mut *e.params as *mut _ as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn clip_define(h: Handle, cname: *const c_char, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(e) = effect(h) else { return STAT_ERR_BAD_HANDLE; };
+    let mut c = Box::new(Clip { name: name(cname).to_owned(), ..Default::default() });
+    c.props.set_str("OfxPropType", "OfxTypeClip");
+    c.props.set_str("OfxPropName", name(cname));
+    *prop_handle = &mut c.props as *mut _ as Handle;
+    e.clips.push(c);
+    STAT_OK
+}
+unsafe extern "C" fn clip_get_handle(h: Handle, cname: *const c_char, clip_handle: *mut Handle, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(e) = effect(h) else { return STAT_ERR_BAD_HANDLE; };
+    let Some(c) = e.clip_mut(name(cname)) else { return STAT_ERR_UNKNOWN; };
+    *clip_handle = c as *mut _ as Handle;
+    if !prop_handle.is_null() {
+        *prop_handle = &mut c.props as *mut _ as Handle;
+    }
+    STAT_OK
+}
+unsafe extern "C" fn clip_get_property_set(h: Handle, prop_handle: *mut Handle) -> OfxStatus {
+    let Some(c) = clip(h) else { return STAT_ERR_BAD_HANDLE; };
+    *prop_handle = &mut c.props as *mut _ as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn clip_get_image(h: Handle, time: f64, _region: *const OfxRectD, image_handle: *mut Handle) -> OfxStatus {
+    let Some(c) = clip(h) else { return STAT_ERR_BAD_HANDLE; };
+    match c.image.as_mut() {
+        Some(img) => {
+            // The same frame is returned for every time, the time is only recorded for the assertions
+            img.props.set_double("OfxPropTime", time);
+            *image_handle = &mut img.props as *mut _ as Handle;
+            STAT_OK
+        },
+        None => STAT_FAILED
+    }
+}
+unsafe extern "C" fn clip_release_image(_image_handle: Handle) -> OfxStatus { STAT_OK }
+unsafe extern "C" fn clip_get_region_of_definition(h: Handle, _time: f64, bounds: *mut OfxRectD) -> OfxStatus {
+    let Some(c) = clip(h) else { return STAT_ERR_BAD_HANDLE; };
+    let rod = c.image.as_ref().map(|x| x.props.get_doubles("OfxImagePropRegionOfDefinition")).unwrap_or_default();
+    *bounds = if rod.len() == 4 { OfxRectD { x1: rod[0], y1: rod[1], x2: rod[2], y2: rod[3] } } else { OfxRectD::default() };
+    STAT_OK
+}
+unsafe extern "C" fn abort(h: Handle) -> c_int {
+    effect(h).map(|e| e.abort as c_int).unwrap_or_default()
+}
+unsafe extern "C" fn image_memory_alloc(_h: Handle, bytes: usize, memory: *mut Handle) -> OfxStatus {
+    *memory = Box::into_raw(Box::new(vec![0u8; bytes])) as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn image_memory_free(memory: Handle) -> OfxStatus {
+    if !memory.is_null() { drop(Box::from_raw(memory as *mut Vec<u8>)); }
+    STAT_OK
+}
+unsafe extern "C" fn image_memory_lock(memory: Handle, ptr: *mut Handle) -> OfxStatus {
+    let Some(v) = (memory as *mut Vec<u8>).as_mut() else { return STAT_ERR_BAD_HANDLE; };
+    *ptr = v.as_mut_ptr() as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn image_memory_unlock(_memory: Handle) -> OfxStatus { STAT_OK }
+
+#[repr(C)]
+pub struct OfxImageEffectSuiteV1 {
+    getPropertySet:            unsafe extern "C" fn(Handle, *mut Handle) -> OfxStatus,
+    getParamSet:               unsafe extern "C" fn(Handle, *mut Handle) -> OfxStatus,
+    clipDefine:                unsafe extern "C" fn(Handle, *const c_char, *mut Handle) -> OfxStatus,
+    clipGetHandle:             unsafe extern "C" fn(Handle, *const c_char, *mut Handle, *mut Handle) -> OfxStatus,
+    clipGetPropertySet:        unsafe extern "C" fn(Handle, *mut Handle) -> OfxStatus,
+    clipGetImage:              unsafe extern "C" fn(Handle, f64, *const OfxRectD, *mut Handle) -> OfxStatus,
+    clipReleaseImage:          unsafe extern "C" fn(Handle) -> OfxStatus,
+    clipGetRegionOfDefinition: unsafe extern "C" fn(Handle, f64, *mut OfxRectD) -> OfxStatus,
+    abort:                     unsafe extern "C" fn(Handle) -> c_int,
+    imageMemoryAlloc:          unsafe extern "C" fn(Handle, usize, *mut Handle) -> OfxStatus,
+    imageMemoryFree:           unsafe extern "C" fn(Handle) -> OfxStatus,
+    imageMemoryLock:           unsafe extern "C" fn(Handle, *mut Handle) -> OfxStatus,
+    imageMemoryUnlock:         unsafe extern "C" fn(Handle) -> OfxStatus,
+}
+pub static IMAGE_EFFECT_SUITE: OfxImageEffectSuiteV1 = OfxImageEffectSuiteV1 {
+    getPropertySet:            get_property_set,
+    getParamSet:               get_param_set,
+    clipDefine:                clip_define,
+    clipGetHandle:             clip_get_handle,
+    clipGetPropertySet:        clip_get_property_set,
+    clipGetImage:              clip_get_image,
+    clipReleaseImage:          clip_release_image,
+    clipGetRegionOfDefinition: clip_get_region_of_definition,
+    abort:                     abort,
+    imageMemoryAlloc:          image_memory_alloc,
+    imageMemoryFree:           image_memory_free,
+    imageMemoryLock:           image_memory_lock,
+    imageMemoryUnlock:         image_memory_unlock,
+};
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Memory, multi-thread and message suites
+// ---------------------------------------------------------------------------------------------------------------------
+
+const MEMORY_HEADER: usize = 16;
+
+unsafe extern "C" fn memory_alloc(_h: Handle, bytes: usize, data: *mut Handle) -> OfxStatus {
+    let layout = std::alloc::Layout::from_size_align_unchecked(bytes + MEMORY_HEADER, MEMORY_HEADER);
+    let ptr = std::alloc::alloc(layout);
+    if ptr.is_null() { return STAT_FAILED; }
+    *(ptr as *mut usize) = bytes;
+    *data = ptr.add(MEMORY_HEADER) as Handle;
+    STAT_OK
+}
+unsafe extern "C" fn memory_free(data: Handle) -> OfxStatus {
+    if data.is_null() { return STAT_OK; }
+    let ptr = (data as *mut u8).sub(MEMORY_HEADER);
+    let layout = std::alloc::Layout::from_size_align_unchecked(*(ptr as *mut usize) + MEMORY_HEADER, MEMORY_HEADER);
+    std::alloc::dealloc(ptr, layout);
+    STAT_OK
+}
+
+#[repr(C)]
+pub struct OfxMemorySuiteV1 {
+    memoryAlloc: unsafe extern "C" fn(Handle, usize, *mut Handle) -> OfxStatus,
+    memoryFree:  unsafe extern "C" fn(Handle) -> OfxStatus,
+}
+pub static MEMORY_SUITE: OfxMemorySuiteV1 = OfxMemorySuiteV1 {
+    memoryAlloc: memory_alloc,
+    memoryFree:  memory_free,
+};
+
+type OfxThreadFunction = unsafe extern "C" fn(thread_index: c_uint, thread_max: c_uint, custom_arg: *mut c_void);
+
+unsafe extern "C" fn multi_thread(func: OfxThreadFunction, n_threads: c_uint, custom_arg: *mut c_void) -> OfxStatus {
+    let n = n_threads.max(1);
+    for i in 0..n { func(i, n, custom_arg); }
+    STAT_OK
+}
+unsafe extern "C" fn multi_thread_num_cpus(n: *mut c_uint) -> OfxStatus { *n = 1; STAT_OK }
+unsafe extern "C" fn multi_thread_index(i: *mut c_uint) -> OfxStatus { *i = 0; STAT_OK }
+unsafe extern "C" fn multi_thread_is_spawned_thread() -> c_int { 0 }
+unsafe extern "C" fn mutex_create(mutex: *mut Handle, _lock_count: c_int) -> OfxStatus { *mutex = std::ptr::NonNull::<u8>::dangling().as_ptr() as Handle; STAT_OK }
+unsafe extern "C" fn mutex_op(_mutex: Handle) -> OfxStatus { STAT_OK }
+
+#[repr(C)]
+pub struct OfxMultiThreadSuiteV1 {
+    multiThread:                unsafe extern "C" fn(OfxThreadFunction, c_uint, *mut c_void) -> OfxStatus,
+    multiThreadNumCPUs:         unsafe extern "C" fn(*mut c_uint) -> OfxStatus,
+    multiThreadIndex:           unsafe extern "C" fn(*mut c_uint) -> OfxStatus,
+    multiThreadIsSpawnedThread: unsafe extern "C" fn() -> c_int,
+    mutexCreate:                unsafe extern "C" fn(*mut Handle, c_int) -> OfxStatus,
+    mutexDestroy:               unsafe extern "C" fn(Handle) -> OfxStatus,
+    mutexLock:                  unsafe extern "C" fn(Handle) -> OfxStatus,
+    mutexUnLock:                unsafe extern "C" fn(Handle) -> OfxStatus,
+    mutexTryLock:               unsafe extern "C" fn(Handle) -> OfxStatus,
+}
+pub static MULTI_THREAD_SUITE: OfxMultiThreadSuiteV1 = OfxMultiThreadSuiteV1 {
+    multiThread:                multi_thread,
+    multiThreadNumCPUs:         multi_thread_num_cpus,
+    multiThreadIndex:           multi_thread_index,
+    multiThreadIsSpawnedThread: multi_thread_is_spawned_thread,
+    mutexCreate:                mutex_create,
+    mutexDestroy:               mutex_op,
+    mutexLock:                  mutex_op,
+    mutexUnLock:                mutex_op,
+    mutexTryLock:               mutex_op,
+};
+
+unsafe extern "C" fn message(_h: Handle, typ: *const c_char, _id: *const c_char, format: *const c_char, _args: ...) -> OfxStatus {
+    eprintln!("[ofx message] {}: {}", name(typ), name(format));
+    STAT_OK
+}
+unsafe extern "C" fn clear_persistent_message(_h: Handle) -> OfxStatus { STAT_OK }
+
+#[repr(C)]
+pub struct OfxMessageSuiteV2 {
+    message:                unsafe extern "C" fn(Handle, *const c_char, *const c_char, *const c_char, ...) -> OfxStatus,
+    setPersistentMessage:   unsafe extern "C" fn(Handle, *const c_char, *const c_char, *const c_char, ...) -> OfxStatus,
+    clearPersistentMessage: unsafe extern "C" fn(Handle) -> OfxStatus,
+}
+// V1 is a prefix of V2, so the same table serves both versions
+pub static MESSAGE_SUITE: OfxMessageSuiteV2 = OfxMessageSuiteV2 {
+    message:                message,
+    setPersistentMessage:   message,
+    clearPersistentMessage: clear_persistent_message,
+};
+
+pub unsafe extern "C" fn fetch_suite(_host: Handle, suite_name: *const c_char, suite_version: c_int) -> *const c_void {
+    match (name(suite_name), suite_version) {
+        ("OfxPropertySuite",    1)      => &PROPERTY_SUITE     as *const _ as *const c_void,
+        ("OfxParameterSuite",   1)      => &PARAMETER_SUITE    as *const _ as *const c_void,
+        ("OfxImageEffectSuite", 1)      => &IMAGE_EFFECT_SUITE as *const _ as *const c_void,
+        ("OfxMemorySuite",      1)      => &MEMORY_SUITE       as *const _ as *const c_void,
+        ("OfxMultiThreadSuite", 1)      => &MULTI_THREAD_SUITE as *const _ as *const c_void,
+        ("OfxMessageSuite",     1 | 2)  => &MESSAGE_SUITE      as *const _ as *const c_void,
+        _ => std::ptr::null()
+    }
+}
@@ -0,0 +1,95 @@
+// The test host implements the variadic `paramGetValue` functions of the OFX param suite, which needs nightly Rust.
+// The toolchain is pinned in `rust-toolchain`, so a plain `cargo test` uses it
+#![feature(c_variadic)]
+
+// Pins the output of `process_pixels` as invoked from the plugin's CPU render path.
+// Each synthetic project is rendered at a few frames for every bit depth and compared with the stored reference.
+
+mod common;
+
+use common::*;
+use common::golden::{ self, Tolerance };
+use common::project::SyntheticProject;
+use common::suites::*;
+
+const FRAMES: [f64; 3] = [0.0, 17.0, 45.0];
+
+fn projects() -> Vec<SyntheticProject> {
+    vec![
+        SyntheticProject {
+            name: "shake",
+            ..Default::default()
+        },
+        SyntheticProject {
+            name: "pan_wide_lens",
+            shake_amplitude: [5.0, 5.0, 25.0],
+            constant_rotation: [0.0, 30.0, 0.0],
+            focal_length: 110.0,
+            distortion: [0.12, -0.04, 0.01, 0.0],
+            smoothness: 1.2,
+            fov: 1.3,
+            ..Default::default()
+        },
+        SyntheticProject {
+            name: "no_motion",
+            shake_amplitude: [0.0, 0.0, 0.0],
+            constant_rotation: [0.0, 0.0, 0.0],
+            ..Default::default()
+        },
+    ]
+}
+
+fn tolerance(depth: BitDepth) -> Tolerance {
+    match depth {
+        BitDepth::Byte => Tolerance { mean: 1.0 / 255.0, outlier: 4.0 / 255.0, max_outliers: 0.005 },
+        _              => Tolerance { mean: 0.002,       outlier: 0.015,       max_outliers: 0.005 },
+    }
+}
+
+fn check_depth(depth: BitDepth) {
+    let host = Host::get();
+    let mut failures = Vec::new();
+    for project in projects() {
+        let source = Frame::pattern(project.width, project.height, depth);
+        let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+        assert_eq!(instance.set_string("gyrodata", &project.write().to_string_lossy()), STAT_OK);
+
+        for frame in FRAMES {
+            let (status, output) = instance.render(frame);
+            assert_eq!(status, STAT_OK, "{} frame {frame} at {depth:?}", project.name);
+
+            let name = format!("{}_{:03}_{:?}", project.name, frame as usize, depth).to_lowercase();
+            if let Err(e) = golden::check(&name, &output, tolerance(depth)) {
+                failures.push(e);
+            }
+        }
+    }
+    assert!(failures.is_empty(), "Output differs from the references:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn golden_byte()  { check_depth(BitDepth::Byte); }
+#[test]
+fn golden_short() { check_depth(BitDepth::Short); }
+#[test]
+fn golden_half()  { check_depth(BitDepth::Half); }
+#[test]
+fn golden_float() { check_depth(BitDepth::Float); }
+
+#[test]
+fn no_motion_keeps_center() {
+    // Without any motion and with identity-ish framing, the center of the frame stays where it was
+    let host = Host::get();
+    let project = SyntheticProject { name: "no_motion_center", shake_amplitude: [0.0; 3], constant_rotation: [0.0; 3], distortion: [0.0; 4], ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+
+    let (status, output) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    let (cx, cy) = (project.width / 2, project.height / 2);
+    let (a, b) = (source.pixel(cx, cy), output.pixel(cx, cy));
+    for c in 0..4 {
+        assert!((a[c] - b[c]).abs() < 0.02, "center pixel moved: {a:?} vs {b:?}");
+    }
+}
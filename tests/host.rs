@@ -0,0 +1,764 @@
+// The test host implements the variadic `paramGetValue` functions of the OFX param suite, which needs nightly Rust.
+// The toolchain is pinned in `rust-toolchain`, so a plain `cargo test` uses it
+#![feature(c_variadic)]
+
+mod common;
+
+use common::*;
+use common::project::SyntheticProject;
+use common::suites::*;
+
+fn mean_abs_diff(a: &Frame, b: &Frame) -> f32 {
+    let (a, b) = (a.to_f32(), b.to_f32());
+    a.iter().zip(&b).map(|(a, b)| (a - b).abs()).sum::<f32>() / a.len() as f32
+}
+fn non_zero_pixels(f: &Frame) -> usize {
+    (0..f.height).flat_map(|y| (0..f.width).map(move |x| (x, y))).filter(|(x, y)| f.pixel(*x, *y)[3] > 0.0).count()
+}
+
+#[test]
+fn describe() {
+    let host = Host::get();
+    assert_eq!(host.plugin_identifier(), "nl.smslv.gyroflowofx.fisheyestab_v1");
+
+    let desc = host.descriptor();
+    assert!(desc.clip("Source").is_some());
+    assert!(desc.clip("Output").is_some());
+    for p in ["gyrodata", "Browse", "Status", "FOV", "Smoothness", "LensCorrectionStrength", "UseGyroflowsKeyframes"] {
+        assert!(desc.params.get(p).is_some(), "Missing param {p}");
+    }
+    let depths = desc.props.get_strs("OfxImageEffectPropSupportedPixelDepths");
+    for d in ["OfxBitDepthByte", "OfxBitDepthShort", "OfxBitDepthHalf", "OfxBitDepthFloat"] {
+        assert!(depths.iter().any(|x| x == d), "{d} not advertised: {depths:?}");
+    }
+}
+
+#[test]
+fn render_without_project_fails() {
+    let host = Host::get();
+    let mut instance = host.create_instance(&Frame::pattern(320, 180, BitDepth::Float), 30.0, 60);
+
+    let (status, _) = instance.render(0.0);
+    assert_eq!(status, STAT_FAILED);
+    assert_eq!(instance.label("Status"), "Project not loaded");
+}
+
+#[test]
+fn identity_pass_through() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "identity", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+
+    // The source is shown until a project is loaded
+    assert_eq!(instance.is_identity(0.0).as_deref(), Some("Source"));
+    assert_eq!(instance.label("Status"), "Project not loaded");
+
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, _) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(instance.is_identity(10.0), None);
+
+    // No stabilization, lens correction or zoom
+    instance.set_double("StabilizationAmount", 0.0);
+    instance.set_double("LensCorrectionStrength", 0.0);
+    instance.set_double("FOV", 1.0);
+    let (status, _) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(instance.is_identity(10.0).as_deref(), Some("Source"));
+    assert_eq!(instance.label("Status"), "OK");
+    assert!(instance.hint("Status").contains("passed through"));
+
+    // The overview and the stretch settings change the frame as well
+    for name in ["ToggleOverview", "DisableStretch"] {
+        assert_eq!(instance.set_bool(name, true), STAT_OK);
+        let (status, _) = instance.render(10.0);
+        assert_eq!(status, STAT_OK);
+        assert_eq!(instance.is_identity(10.0), None, "{name}");
+        assert_eq!(instance.set_bool(name, false), STAT_OK);
+    }
+    assert_eq!(instance.is_identity(10.0).as_deref(), Some("Source"));
+}
+
+#[test]
+fn render_cpu_with_project() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "render_cpu", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+
+    assert_eq!(instance.set_string("gyrodata", &project.write().to_string_lossy()), STAT_OK);
+    assert!(!instance.get_string("InstanceId").is_empty());
+
+    let (status, output) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(instance.label("Status"), "OK", "{}", instance.hint("Status"));
+
+    // Most of the frame is covered by the stabilized image, and it's warped compared to the source
+    assert!(non_zero_pixels(&output) > output.width * output.height / 2);
+    assert!(mean_abs_diff(&source, &output) > 0.001);
+
+    // Values imported from the project
+    assert!((instance.get_double("Smoothness") - project.smoothness).abs() < 1e-6);
+    assert!((instance.get_double("FOV") - project.fov).abs() < 1e-6);
+}
+
+#[test]
+fn render_cpu_half_float() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "render_half", ..Default::default() };
+    let path = project.write().to_string_lossy().to_string();
+
+    let float_source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&float_source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &path);
+    let (status, float_output) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    drop(instance);
+
+    let half_source = Frame::pattern(project.width, project.height, BitDepth::Half);
+    let mut instance = host.create_instance(&half_source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &path);
+    let (status, half_output) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(half_output.depth, BitDepth::Half);
+
+    // Same result as the float pipeline, within the f16 precision
+    assert!(non_zero_pixels(&half_output) > half_output.width * half_output.height / 2);
+    assert!(mean_abs_diff(&float_output, &half_output) < 0.005);
+}
+
+#[test]
+fn render_negative_row_bytes() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "render_top_down", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Half);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, expected) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+
+    // Same frame when the host stores the rows top-down, for full frames and tiles
+    instance.top_down = true;
+    instance.set_source(source);
+    let (status, output) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&expected, &output) < 0.001);
+
+    let window = [0, 0, project.width / 2, project.height / 2];
+    let (status, tile) = instance.render_tile(10.0, window);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&expected.crop(window), &tile) < 0.001);
+}
+
+#[test]
+fn render_rgb_and_alpha_components() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "render_components", ..Default::default() };
+    let path = project.write().to_string_lossy().to_string();
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &path);
+    let (status, rgba) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    drop(instance);
+
+    for (channels, components) in [(3, "OfxImageComponentRGB"), (1, "OfxImageComponentAlpha")] {
+        let mut instance = host.create_instance(&source.to_channels(channels), project.fps, project.num_frames);
+        instance.set_string("gyrodata", &path);
+
+        // The output follows the source, including its depth, and the source isn't converted to RGBA
+        let prefs = instance.clip_preferences();
+        assert_eq!(prefs.get_str("OfxImageClipPropComponents_Source").as_deref(), Some(components));
+        assert_eq!(prefs.get_str("OfxImageClipPropComponents_Output").as_deref(), Some(components));
+        assert_eq!(prefs.get_str("OfxImageClipPropDepth_Output").as_deref(), Some("OfxBitDepthFloat"));
+
+        let (status, output) = instance.render(10.0);
+        assert_eq!(status, STAT_OK);
+        assert_eq!(output.channels, channels);
+        // Same geometry as the RGBA render, in the channels which are there
+        assert!(mean_abs_diff(&rgba.to_channels(channels), &output) < 0.001, "{components} output differs from RGBA");
+    }
+}
+
+#[test]
+fn tiled_render() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "tiled_render", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+
+    let (status, full) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+
+    // The tiles are copied from one render of the whole frame, so they all need the same part of the source
+    let (w, h) = (project.width / 2, project.height / 2);
+    let roi = instance.regions_of_interest(10.0, [0.0, 0.0, w as f64, h as f64]);
+    assert_eq!(instance.regions_of_interest(10.0, [w as f64, h as f64, (w * 2) as f64, (h * 2) as f64]), roi);
+
+    // Rendered from that part only, every tile is the same as in the full frame
+    for window in [[0, 0, w, h], [w, 0, w * 2, h], [0, h, w, h * 2], [w, h, w * 2, h * 2]] {
+        let (status, tile) = instance.render_tile(10.0, window);
+        assert_eq!(status, STAT_OK);
+        assert!(mean_abs_diff(&full.crop(window), &tile) < 0.001, "{window:?}");
+    }
+}
+
+#[test]
+fn instance_changed_recomputes() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "instance_changed", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+
+    let (status, before) = instance.render(20.0);
+    assert_eq!(status, STAT_OK);
+
+    assert_eq!(instance.set_double("FOV", 2.0), STAT_OK);
+    let (status, after) = instance.render(20.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&before, &after) > 0.001, "Changing FOV didn't change the output");
+
+    // Rendering the same frame again with the same settings is deterministic
+    let (_, again) = instance.render(20.0);
+    assert_eq!(after.data, again.data);
+}
+
+#[test]
+fn auto_reload_on_project_change() {
+    let host = Host::get();
+    let mut project = SyntheticProject { name: "auto_reload", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let path = project.write().to_string_lossy().to_string();
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &path);
+    assert_eq!(instance.set_bool("AutoReload", true), STAT_OK);
+
+    let (status, before) = instance.render(15.0);
+    assert_eq!(status, STAT_OK);
+    instance.set_double("Smoothness", 0.8);
+
+    // Another piece of the clip watches the same file
+    let mut other = host.create_instance(&source, project.fps, project.num_frames);
+    other.set_string("gyrodata", &path);
+    assert_eq!(other.set_bool("AutoReload", true), STAT_OK);
+    let (status, other_before) = other.render(15.0);
+    assert_eq!(status, STAT_OK);
+    let revisions = (instance.get_double("RenderRevision"), other.get_double("RenderRevision"));
+
+    // Saved again with different motion and values
+    project.shake_amplitude = [0.0, 0.0, 40.0];
+    project.smoothness = 0.2;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    project.write();
+    std::thread::sleep(std::time::Duration::from_millis(2500));
+
+    // Both instances are asked to render again from the next action on the host's thread, and both load the changed file
+    assert_eq!(instance.instance_changed("RenderRevision"), STAT_OK);
+    assert_eq!(other.instance_changed("RenderRevision"), STAT_OK);
+    assert!(instance.get_double("RenderRevision") > revisions.0);
+    assert!(other.get_double("RenderRevision") > revisions.1);
+    let (status, after) = instance.render(15.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&before, &after) > 0.001, "Changed project wasn't reloaded");
+    let (status, other_after) = other.render(15.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&other_before, &other_after) > 0.001, "Changed project wasn't reloaded in the other instance");
+    // Adjusted values are kept unless requested
+    assert!((instance.get_double("Smoothness") - 0.8).abs() < 1e-6);
+}
+
+#[test]
+fn auto_reload_keeps_autosync_offsets() {
+    let host = Host::get();
+    let mut project = SyntheticProject { name: "auto_reload_offsets", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let path = project.write();
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &path.to_string_lossy());
+    assert_eq!(instance.set_bool("AutoReload", true), STAT_OK);
+    let autosync = format!(r#"{{ "path": {:?}, "offsets": {{ "0": 30.0 }} }}"#, path.to_string_lossy());
+    instance.set_string("AutosyncOffsets", &autosync);
+    let (status, _) = instance.render(15.0);
+    assert_eq!(status, STAT_OK);
+
+    // Saved again with the same sync offsets
+    project.smoothness = 0.2;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    project.write();
+    std::thread::sleep(std::time::Duration::from_millis(2500));
+    assert_eq!(instance.instance_changed("RenderRevision"), STAT_OK);
+    let (status, _) = instance.render(15.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(instance.get_string("AutosyncOffsets"), autosync);
+
+    // Synced again in Gyroflow
+    let data = std::fs::read_to_string(&path).unwrap().replace(r#""offsets": { "0": 0.0 }"#, r#""offsets": { "0": 12.0 }"#);
+    std::fs::write(&path, data).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(2500));
+    assert_eq!(instance.instance_changed("RenderRevision"), STAT_OK);
+    let (status, _) = instance.render(15.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(instance.get_string("AutosyncOffsets"), "");
+}
+
+#[test]
+fn interactive_render_loads_in_background() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "background_loading", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.interactive = true;
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+
+    // The source is passed through until the project is loaded
+    let (status, output) = instance.render(10.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(instance.label("Status"), "Loading…");
+    assert_eq!(source.data, output.data);
+
+    let start = std::time::Instant::now();
+    let output = loop {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let (status, output) = instance.render(10.0);
+        assert_eq!(status, STAT_OK);
+        if instance.label("Status") != "Loading…" { break output; }
+        assert!(start.elapsed().as_secs() < 30, "Loading didn't finish");
+    };
+    assert_eq!(instance.label("Status"), "OK", "{}", instance.hint("Status"));
+    assert!(mean_abs_diff(&source, &output) > 0.001);
+    // Finished jobs ask the host to render again, from the next action which can change params
+    assert_eq!(instance.get_double("RenderRevision"), 0.0);
+    assert_eq!(instance.instance_changed("RenderRevision"), STAT_OK);
+    assert!(instance.get_double("RenderRevision") > 0.0);
+}
+
+#[test]
+fn keyframes_are_sampled_sparsely() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "sparse_keyframes", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, 100_000);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    instance.render(0.0);
+
+    // Linear animation over a very long clip only needs a few queries
+    instance.set_double_key("FOV", 0.0, 1.0);
+    instance.set_double_key("FOV", 80.0, 2.0);
+    let before = VALUE_AT_TIME_CALLS.load(std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(instance.set_double_key("FOV", 100_000.0, 2.0), STAT_OK);
+    let calls = VALUE_AT_TIME_CALLS.load(std::sync::atomic::Ordering::SeqCst) - before;
+    assert!(calls < 100, "{calls} queries to cache a linear animation");
+
+    // The cached values follow the host's curve
+    let (status, animated) = instance.render(40.0);
+    assert_eq!(status, STAT_OK);
+    let mut constant = host.create_instance(&source, project.fps, 100_000);
+    constant.set_string("gyrodata", &project.write().to_string_lossy());
+    constant.set_double("FOV", 1.5);
+    let (_, expected) = constant.render(40.0);
+    assert!(mean_abs_diff(&animated, &expected) < 0.001);
+}
+
+#[test]
+fn keyframes_follow_eased_curves() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "eased_keyframes", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, 100_000);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    instance.render(0.0);
+
+    // The curve between the keys is sampled where it's not linear
+    instance.param_mut("FOV").eased = true;
+    instance.set_double_key("FOV", 0.0, 1.0);
+    instance.set_double_key("FOV", 80.0, 2.0);
+    let (status, animated) = instance.render(20.0);
+    assert_eq!(status, STAT_OK);
+    let mut constant = host.create_instance(&source, project.fps, 100_000);
+    constant.set_string("gyrodata", &project.write().to_string_lossy());
+    // Ease in and out at a quarter of the way
+    constant.set_double("FOV", 1.0 + 0.15625);
+    let (_, expected) = constant.render(20.0);
+    assert!(mean_abs_diff(&animated, &expected) < 0.001);
+    drop(constant);
+
+    // A long curve is sampled within the tolerance, not at every frame
+    let before = VALUE_AT_TIME_CALLS.load(std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(instance.set_double_key("FOV", 100_000.0, 1.0), STAT_OK);
+    let calls = VALUE_AT_TIME_CALLS.load(std::sync::atomic::Ordering::SeqCst) - before;
+    assert!(calls < 1000, "{calls} queries to cache an eased animation");
+}
+
+#[test]
+fn save_to_project_round_trip() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "save_to_project", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let path = project.write().to_string_lossy().to_string();
+    {
+        let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+        instance.set_string("gyrodata", &path);
+        instance.render(0.0);
+        instance.set_double("FOV", 1.7);
+        instance.set_double_key("Smoothness", 0.0, 0.3);
+        instance.set_double_key("Smoothness", 30.0, 0.9);
+        assert_eq!(instance.press("SaveProject"), STAT_OK);
+    }
+    // Sections written by Gyroflow which the plugin doesn't know are kept
+    let saved = serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved["output"]["output_width"].as_u64(), Some(project.width as u64));
+
+    // Loading the saved project brings back the values and keyframes
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &path);
+    let (status, _) = instance.render(0.0);
+    assert_eq!(status, STAT_OK);
+    assert!((instance.get_double("FOV") - 1.7).abs() < 1e-6);
+    assert!(instance.get_bool("UseGyroflowsKeyframes"));
+    let keys = &instance.param("Smoothness").keys;
+    assert!(keys.len() >= 2, "{keys:?}");
+}
+
+#[test]
+fn smoothing_algorithm_selection() {
+    let host = Host::get();
+    let algorithms = host.descriptor().params.get("SmoothingAlgorithm").expect("Missing SmoothingAlgorithm").props.get_strs("OfxParamPropChoiceOption");
+    assert!(algorithms.len() > 1, "{algorithms:?}");
+
+    let project = SyntheticProject { name: "smoothing_algorithm", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, before) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    let current = instance.get_int("SmoothingAlgorithm");
+    let other = (0..algorithms.len() as i32).find(|x| *x != current).unwrap();
+    assert_eq!(instance.set_int("SmoothingAlgorithm", other), STAT_OK);
+    let (status, after) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&before, &after) > 0.001, "Changing the algorithm from {} to {} didn't change the output", algorithms[current as usize], algorithms[other as usize]);
+
+    // The algorithm is selected by its name when the project is opened, even if its index changed
+    instance.param_mut("SmoothingAlgorithm").value = ParamValue::Int(vec![current]);
+    instance.reopen();
+    assert_eq!(instance.get_int("SmoothingAlgorithm"), other);
+}
+
+#[test]
+fn per_axis_smoothness() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "per_axis", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, before) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    assert_eq!(instance.set_bool("PerAxisSmoothness", true), STAT_OK);
+    instance.set_double("SmoothnessRoll", 3.0);
+    let (status, locked_roll) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&before, &locked_roll) > 0.001, "Roll smoothness didn't change the output");
+
+    // Keyframed per-axis values are cached like the others
+    instance.set_double_key("SmoothnessRoll", 0.0, 3.0);
+    instance.set_double_key("SmoothnessRoll", 50.0, 3.0);
+    let (_, keyed) = instance.render(25.0);
+    assert!(mean_abs_diff(&locked_roll, &keyed) < 0.001);
+}
+
+#[test]
+fn zoom_mode() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "zoom_mode", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, disabled) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    // The synthetic project has zooming disabled
+    assert_eq!(instance.get_int("ZoomMode"), 0);
+
+    // Zooming in to hide the borders changes the framing
+    assert_eq!(instance.set_int("ZoomMode", 2), STAT_OK);
+    let (status, static_crop) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&disabled, &static_crop) > 0.001);
+
+    assert_eq!(instance.set_int("ZoomMode", 1), STAT_OK);
+    instance.set_double("ZoomWindow", 0.5);
+    let (status, dynamic) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&static_crop, &dynamic) > 0.0001);
+
+    // The default uses the zooming of the project
+    assert_eq!(host.create_instance(&source, project.fps, project.num_frames).get_int("ZoomMode"), 3);
+    assert_eq!(instance.set_int("ZoomMode", 3), STAT_OK);
+    let (status, from_project) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&disabled, &from_project) < 0.0001);
+}
+
+#[test]
+fn max_zoom_limits_adaptive_zoom() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "max_zoom", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    assert_eq!(instance.set_int("ZoomMode", 2), STAT_OK);
+    let (status, unlimited) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(instance.hint("Status"), "OK");
+
+    // Barely any zoom allowed, so the static crop is clamped
+    assert_eq!(instance.set_double("MaxZoom", 100.5), STAT_OK);
+    let (status, limited) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&unlimited, &limited) > 0.001);
+    assert!(instance.hint("Status").contains("Max zoom"));
+
+    assert_eq!(instance.set_double("MaxZoom", 0.0), STAT_OK);
+    let (_, unlimited_again) = instance.render(25.0);
+    assert!(mean_abs_diff(&unlimited, &unlimited_again) < 0.0001);
+}
+
+#[test]
+fn analyze_crop() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "analyze_crop", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    assert_eq!(instance.set_int("ZoomMode", 2), STAT_OK);
+    let (status, _) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    // The safe FOV replaces the adaptive zoom
+    assert_eq!(instance.press("AnalyzeCrop"), STAT_OK);
+    let fov = instance.get_double("FOV");
+    assert!(fov < 1.0 && fov > 0.1);
+    assert_eq!(instance.get_int("ZoomMode"), 0);
+    assert!(instance.param("FOV").keys.is_empty());
+
+    // The FOV set for the adaptive zoom is kept as a multiplier
+    assert_eq!(instance.set_int("ZoomMode", 2), STAT_OK);
+    instance.set_double("FOV", 0.9);
+    assert_eq!(instance.press("AnalyzeCrop"), STAT_OK);
+    assert!((instance.get_double("FOV") - fov * 0.9).abs() < 1e-3, "{} {fov}", instance.get_double("FOV"));
+
+    assert_eq!(instance.set_int("AnalyzeCropMode", 1), STAT_OK);
+    instance.set_double("AnalyzeSegmentLength", 1.0);
+    assert_eq!(instance.press("AnalyzeCrop"), STAT_OK);
+    let keys = &instance.param("FOV").keys;
+    assert!(keys.len() >= 2);
+    // No segment needs more zoom than the whole clip
+    assert!(keys.iter().all(|(_, v)| matches!(v, ParamValue::Double(v) if v[0] >= fov - 1e-6 && v[0] <= 1.0)));
+    let (status, _) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+}
+
+#[test]
+fn horizon_lock_pitch() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "horizon_lock_pitch", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    instance.set_double("HorizonLockAmount", 100.0);
+    instance.set_double("HorizonLockPitch", 20.0);
+    let (status, roll_only) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    // The pitch angle is used only when locking the pitch
+    assert_eq!(instance.set_int("HorizonLockMode", 1), STAT_OK);
+    let (status, pitch_locked) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&roll_only, &pitch_locked) > 0.001);
+
+    instance.set_double_key("HorizonLockPitch", 0.0, -20.0);
+    instance.set_double_key("HorizonLockPitch", 50.0, 20.0);
+    let (status, animated) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&pitch_locked, &animated) > 0.001);
+}
+
+#[test]
+fn stabilization_amount() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "stabilization_amount", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    instance.set_double("LensCorrectionStrength", 0.0);
+    let (status, stabilized) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    // Without stabilization and lens correction, the source is passed through
+    instance.set_double("StabilizationAmount", 0.0);
+    let (status, raw) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&stabilized, &raw) > 0.001);
+    assert!(mean_abs_diff(&source, &raw) < mean_abs_diff(&source, &stabilized));
+
+    // Faded in across the clip, it's also applied with Gyroflow's keyframes
+    instance.set_double_key("StabilizationAmount", 0.0, 0.0);
+    instance.set_double_key("StabilizationAmount", 50.0, 100.0);
+    instance.set_bool("UseGyroflowsKeyframes", true);
+    let (status, half) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&half, &raw) > 0.0001);
+    assert!(mean_abs_diff(&half, &stabilized) > 0.0001);
+}
+
+#[test]
+fn sync_offset() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "sync_offset", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let path = project.write().to_string_lossy().to_string();
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &path);
+    // As if autosync found an offset, which isn't in the file yet
+    instance.set_string("AutosyncOffsets", &format!(r#"{{ "path": {path:?}, "offsets": {{ "0": 30.0 }} }}"#));
+    let (status, synced) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    assert_eq!(instance.set_double("SyncOffset", 80.0), STAT_OK);
+    let (status, shifted) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&synced, &shifted) > 0.001);
+
+    // Saving bakes the offset into the project, so the result stays the same
+    assert_eq!(instance.press("SaveProject"), STAT_OK);
+    assert_eq!(instance.get_double("SyncOffset"), 0.0);
+    assert_eq!(instance.get_string("AutosyncOffsets"), "");
+    let (status, saved) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&shifted, &saved) < 0.0001);
+    let data = std::fs::read_to_string(&path).unwrap();
+    assert!(data.contains("offsets"));
+}
+
+#[test]
+fn autosync() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "autosync", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, _) = instance.render(0.0);
+    assert_eq!(status, STAT_OK);
+
+    // Frames around the sync points are read and synced in the background, pressing the button doesn't block
+    assert_eq!(instance.press("Autosync"), STAT_OK);
+
+    // A static frame can't be synced but the clip keeps rendering
+    let start = std::time::Instant::now();
+    loop {
+        let (status, _) = instance.render(0.0);
+        assert_eq!(status, STAT_OK);
+        if instance.label("Status") != "Syncing..." || start.elapsed().as_secs() > 60 { break; }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert_ne!(instance.label("Status"), "Syncing...");
+    // The frames were read, only the sync itself failed
+    assert!(!instance.hint("Status").contains("Failed to read frame"));
+}
+
+#[test]
+fn motion_data_file() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "motion_data_file", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, before) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    // Only used for video files, projects already have their motion data
+    assert_eq!(instance.set_string("MotionDataFile", "/nonexistent/log.gcsv"), STAT_OK);
+    let (status, after) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert!(mean_abs_diff(&before, &after) < 0.0001);
+
+    // A video with a missing log and nothing embedded can't be loaded
+    instance.set_string("gyrodata", "/nonexistent/video.mp4");
+    let (status, _) = instance.render(25.0);
+    assert_ne!(status, STAT_OK);
+    // Then the host shows the source instead of failed frames
+    assert_eq!(instance.is_identity(25.0).as_deref(), Some("Source"));
+}
+
+#[test]
+fn lens_profile_search() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "lens_profile_search", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+
+    // The bundled profiles are searched while typing, nothing is applied until a match is selected
+    assert_eq!(instance.set_string("LensSearch", "gopro hero"), STAT_OK);
+    assert!(instance.get_string("EmbeddedLensProfile").is_empty());
+    assert!(instance.hint("LensSearch").contains("matching lens profiles"));
+    assert_eq!(instance.press("NextLens"), STAT_OK);
+    let first = instance.get_string("LensProfileName");
+    assert!(first.to_lowercase().contains("gopro") && first.contains("(1/"), "{first}");
+    let lens = instance.get_string("EmbeddedLensProfile");
+    assert!(lens.contains("calib_dimension"));
+
+    assert_eq!(instance.press("NextLens"), STAT_OK);
+    assert!(instance.get_string("LensProfileName").contains("(2/"));
+    assert_ne!(instance.get_string("EmbeddedLensProfile"), lens);
+    assert_eq!(instance.press("PreviousLens"), STAT_OK);
+    assert_eq!(instance.get_string("LensProfileName"), first);
+
+    assert_eq!(instance.set_string("LensSearch", "no such camera"), STAT_OK);
+    assert_eq!(instance.hint("LensSearch"), "No matching lens profiles");
+    assert_eq!(instance.get_string("LensProfileName"), first);
+    assert_eq!(instance.press("NextLens"), STAT_OK);
+    assert_eq!(instance.get_string("LensProfileName"), "No matching lens profiles");
+}
+
+#[test]
+fn interpolation() {
+    let host = Host::get();
+    let project = SyntheticProject { name: "interpolation", ..Default::default() };
+    let source = Frame::pattern(project.width, project.height, BitDepth::Float);
+    let mut instance = host.create_instance(&source, project.fps, project.num_frames);
+    instance.set_string("gyrodata", &project.write().to_string_lossy());
+    let (status, final_render) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+
+    // Scrubbing uses a cheaper kernel, set up in the background
+    instance.interactive = true;
+    let start = std::time::Instant::now();
+    let draft = loop {
+        let (status, output) = instance.render(25.0);
+        assert_eq!(status, STAT_OK);
+        if instance.label("Status") != "Loading…" { break output; }
+        assert!(start.elapsed().as_secs() < 30, "Loading didn't finish");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+    assert!(mean_abs_diff(&final_render, &draft) > 0.0);
+    assert!(mean_abs_diff(&final_render, &draft) < 0.05);
+
+    // Draft renders use the cheap kernel with the same manager, so they don't wait for the loading
+    instance.interactive = false;
+    instance.draft = true;
+    let (status, draft_render) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(draft_render.data, draft.data);
+    instance.draft = false;
+
+    // A fixed choice is used for all of them, and the manager is still cached
+    assert_eq!(instance.set_int("Interpolation", 3), STAT_OK);
+    let (status, lanczos) = instance.render(25.0);
+    assert_eq!(status, STAT_OK);
+    assert_eq!(final_render.data, lanczos.data);
+}